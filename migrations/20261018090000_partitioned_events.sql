@@ -0,0 +1,90 @@
+CREATE TABLE IF NOT EXISTS akari_events_partitioned (
+    event BIGINT NOT NULL,
+    time BIGINT NOT NULL,
+    actor TEXT,
+    receptor TEXT,
+    origin TEXT,
+    destination TEXT,
+    category TEXT NOT NULL,
+    data TEXT[],
+    PRIMARY KEY (time, event)
+) PARTITION BY RANGE (time);
+
+CREATE TABLE IF NOT EXISTS akari_events_partitioned_default
+    PARTITION OF akari_events_partitioned DEFAULT;
+
+CREATE INDEX IF NOT EXISTS akari_events_partitioned_event_idx ON akari_events_partitioned (event);
+CREATE INDEX IF NOT EXISTS akari_events_partitioned_actor_idx ON akari_events_partitioned (actor);
+CREATE INDEX IF NOT EXISTS akari_events_partitioned_receptor_idx ON akari_events_partitioned (receptor);
+CREATE INDEX IF NOT EXISTS akari_events_partitioned_origin_idx ON akari_events_partitioned (origin);
+CREATE INDEX IF NOT EXISTS akari_events_partitioned_destination_idx ON akari_events_partitioned (destination);
+CREATE INDEX IF NOT EXISTS akari_events_partitioned_category_event_idx ON akari_events_partitioned (category, event);
+-- Per-category retention deletes by category and time
+CREATE INDEX IF NOT EXISTS akari_events_partitioned_category_time_idx ON akari_events_partitioned (category, time);
+
+-- Bookkeeping for the partitions created by Akari, so retention doesn't need to parse partition bounds
+CREATE TABLE IF NOT EXISTS akari_partitions (
+    name TEXT PRIMARY KEY,
+    range_start BIGINT NOT NULL,
+    range_end BIGINT NOT NULL
+);
+
+-- Creates the partitions covering the current period and the following `premake` periods.
+-- Rows that already landed in the default partition for a new range are moved into it.
+CREATE OR REPLACE FUNCTION akari_create_partitions(step TEXT, premake INTEGER) RETURNS INTEGER AS $$
+DECLARE
+    period INTERVAL := ('1 ' || step)::INTERVAL;
+    period_start TIMESTAMPTZ := date_trunc(step, now(), 'UTC');
+    range_start BIGINT;
+    range_end BIGINT;
+    partition_name TEXT;
+    created INTEGER := 0;
+BEGIN
+    FOR i IN 0..premake LOOP
+        range_start := extract(epoch FROM period_start + period * i)::BIGINT;
+        range_end := extract(epoch FROM period_start + period * (i + 1))::BIGINT;
+        partition_name := 'akari_events_p' || to_char((period_start + period * i) AT TIME ZONE 'UTC', 'YYYYMMDD');
+
+        CONTINUE WHEN EXISTS (SELECT 1 FROM akari_partitions p WHERE p.name = partition_name);
+
+        EXECUTE format('CREATE TABLE %I (LIKE akari_events_partitioned INCLUDING DEFAULTS)', partition_name);
+        EXECUTE format(
+            'WITH moved AS (DELETE FROM akari_events_partitioned_default WHERE time >= %s AND time < %s RETURNING *)
+            INSERT INTO %I SELECT * FROM moved', range_start, range_end, partition_name
+        );
+        EXECUTE format(
+            'ALTER TABLE akari_events_partitioned ATTACH PARTITION %I FOR VALUES FROM (%s) TO (%s)',
+            partition_name, range_start, range_end
+        );
+
+        INSERT INTO akari_partitions (name, range_start, range_end) VALUES (partition_name, range_start, range_end);
+        created := created + 1;
+    END LOOP;
+
+    RETURN created;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Drops (or detaches, leaving the table in place for archival) every partition that ends before `horizon`,
+-- and prunes expired rows that ended up in the default partition.
+CREATE OR REPLACE FUNCTION akari_apply_retention(horizon BIGINT, detach BOOLEAN) RETURNS INTEGER AS $$
+DECLARE
+    expired RECORD;
+    removed INTEGER := 0;
+BEGIN
+    FOR expired IN SELECT name FROM akari_partitions WHERE range_end <= horizon ORDER BY range_start LOOP
+        IF detach THEN
+            EXECUTE format('ALTER TABLE akari_events_partitioned DETACH PARTITION %I', expired.name);
+        ELSE
+            EXECUTE format('DROP TABLE %I', expired.name);
+        END IF;
+
+        DELETE FROM akari_partitions WHERE name = expired.name;
+        removed := removed + 1;
+    END LOOP;
+
+    DELETE FROM akari_events_partitioned_default WHERE time < horizon;
+
+    RETURN removed;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS akari_rmb_posts (
+    post_id BIGINT PRIMARY KEY,
+    event BIGINT NOT NULL,
+    time BIGINT NOT NULL,
+    nation TEXT NOT NULL,
+    region TEXT NOT NULL,
+    message TEXT NOT NULL,
+    search TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', message)) STORED
+);
+
+CREATE INDEX IF NOT EXISTS akari_rmb_posts_event_idx ON akari_rmb_posts (time, event);
+CREATE INDEX IF NOT EXISTS akari_rmb_posts_nation_idx ON akari_rmb_posts (nation);
+CREATE INDEX IF NOT EXISTS akari_rmb_posts_region_idx ON akari_rmb_posts (region, post_id);
+CREATE INDEX IF NOT EXISTS akari_rmb_posts_search_idx ON akari_rmb_posts USING GIN (search);
@@ -0,0 +1,2 @@
+ALTER TABLE akari_events ADD COLUMN IF NOT EXISTS origin_inferred BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE akari_events_partitioned ADD COLUMN IF NOT EXISTS origin_inferred BOOLEAN NOT NULL DEFAULT FALSE;
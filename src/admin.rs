@@ -0,0 +1,227 @@
+use std::{collections::BTreeMap, error::Error, fs, os::unix::fs::FileTypeExt, sync::Arc};
+use axum::{
+    Json, Router, extract::{Path, State}, http::StatusCode, response::{IntoResponse, Response}, routing::{get, post, put}
+};
+use log::{error, info};
+use opentelemetry::trace::TraceContextExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, mpsc, oneshot};
+
+use crate::config::Config;
+use crate::events::{ServerEvent, now_timestamp, now_timestamp_millis};
+use crate::monitoring::metrics;
+use crate::output::{FilterSummary, OutputChannelFilter};
+use crate::queue::WeakEventQueue;
+use crate::telemetry;
+use crate::worker::{OutputCommand, OutputStatus, Workers};
+
+const DEFAULT_BIND: &str = "127.0.0.1:9091";
+
+struct AdminState {
+    // Weak, so an open admin connection can't keep the workers from shutting down
+    queue: WeakEventQueue,
+    outputs: mpsc::UnboundedSender<OutputCommand>,
+    reconnect: Arc<Notify>,
+}
+
+struct AdminError(StatusCode, String);
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct OutputInfo {
+    name: String,
+    paused: bool,
+    queued: usize,
+    #[serde(flatten)]
+    filter: FilterSummary,
+}
+
+impl From<OutputStatus> for OutputInfo {
+    fn from(status: OutputStatus) -> Self {
+        Self {
+            name: status.control.name.clone(),
+            paused: status.control.is_paused(),
+            queued: status.queued,
+            filter: status.control.filter(),
+        }
+    }
+}
+
+// Replaces every filter of an output, missing fields remove that filter
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilterUpdate {
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    filter: Option<String>,
+}
+
+// A happening line, handled as if it had been received from NationStates
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Injection {
+    line: String,
+    #[serde(default)]
+    regions: Vec<String>,
+    rmb_message: Option<String>,
+    // Defaults to 0, which NationStates never uses
+    id: Option<i64>,
+    time: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct QueueDepths {
+    work: i64,
+    result: i64,
+    spill: i64,
+    outputs: BTreeMap<String, usize>,
+}
+
+pub async fn initialize(config: &Config, workers: &Workers, reconnect: Arc<Notify>) -> Result<(), Box<dyn Error>> {
+    let Some(admin_config) = &config.admin else {
+        return Ok(());
+    };
+
+    if !admin_config.enabled { return Ok(()); }
+
+    let state = Arc::new(AdminState {
+        queue: workers.events.downgrade(),
+        outputs: workers.outputs.clone(),
+        reconnect,
+    });
+
+    let router = Router::new()
+        .route("/outputs", get(serve_outputs))
+        .route("/outputs/{name}/pause", post(pause_output))
+        .route("/outputs/{name}/resume", post(resume_output))
+        .route("/outputs/{name}/filter", put(set_filter))
+        .route("/queues", get(serve_queues))
+        .route("/reconnect", post(request_reconnect))
+        .route("/inject", post(inject))
+        .with_state(state);
+
+    if let Some(socket) = &admin_config.socket {
+        // Left behind if the previous run didn't shut down cleanly
+        if fs::symlink_metadata(socket).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            fs::remove_file(socket)?;
+        }
+
+        let listener = tokio::net::UnixListener::bind(socket)?;
+        info!("Admin API listening on {}", socket);
+
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                error!("Admin API server stopped: {}", err);
+            }
+        });
+    } else {
+        let bind = admin_config.bind.as_deref().unwrap_or(DEFAULT_BIND);
+        let listener = tokio::net::TcpListener::bind(bind).await?;
+        info!("Admin API listening on {}", bind);
+
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                error!("Admin API server stopped: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn list_outputs(state: &AdminState) -> Result<Vec<OutputStatus>, AdminError> {
+    let (reply, outputs) = oneshot::channel();
+    let unavailable = || AdminError(StatusCode::SERVICE_UNAVAILABLE, "the output worker is not running".into());
+
+    state.outputs.send(OutputCommand::List(reply)).map_err(|_| unavailable())?;
+    outputs.await.map_err(|_| unavailable())
+}
+
+async fn find_output(state: &AdminState, name: &str) -> Result<OutputStatus, AdminError> {
+    list_outputs(state).await?.into_iter()
+        .find(|status| status.control.name == name)
+        .ok_or_else(|| AdminError(StatusCode::NOT_FOUND, format!("no running output named '{}'", name)))
+}
+
+async fn serve_outputs(State(state): State<Arc<AdminState>>) -> Result<Json<Vec<OutputInfo>>, AdminError> {
+    Ok(Json(list_outputs(&state).await?.into_iter().map(OutputInfo::from).collect()))
+}
+
+async fn pause_output(State(state): State<Arc<AdminState>>, Path(name): Path<String>) -> Result<Json<OutputInfo>, AdminError> {
+    let output = find_output(&state, &name).await?;
+    output.control.set_paused(true);
+    info!(output = name.as_str(); "Output '{}' paused through the admin API", name);
+
+    Ok(Json(output.into()))
+}
+
+async fn resume_output(State(state): State<Arc<AdminState>>, Path(name): Path<String>) -> Result<Json<OutputInfo>, AdminError> {
+    let output = find_output(&state, &name).await?;
+    output.control.set_paused(false);
+    info!(output = name.as_str(); "Output '{}' resumed through the admin API", name);
+
+    Ok(Json(output.into()))
+}
+
+async fn set_filter(
+    State(state): State<Arc<AdminState>>,
+    Path(name): Path<String>,
+    Json(update): Json<FilterUpdate>,
+) -> Result<Json<OutputInfo>, AdminError> {
+    let output = find_output(&state, &name).await?;
+
+    let filter = OutputChannelFilter::new(update.include, update.exclude, update.filter.as_deref())
+        .map_err(|err| AdminError(StatusCode::BAD_REQUEST, err.to_string()))?;
+
+    output.control.set_filter(filter);
+    info!(output = name.as_str(); "Filters of output '{}' changed through the admin API", name);
+
+    Ok(Json(output.into()))
+}
+
+async fn serve_queues(State(state): State<Arc<AdminState>>) -> Result<Json<QueueDepths>, AdminError> {
+    let depth = |queue| metrics::QUEUE_DEPTH.with_label_values(&[queue]).get();
+
+    Ok(Json(QueueDepths {
+        work: depth("work"),
+        result: depth("result"),
+        spill: depth("spill"),
+        outputs: list_outputs(&state).await?.into_iter().map(|status| (status.control.name.clone(), status.queued)).collect(),
+    }))
+}
+
+async fn request_reconnect(State(state): State<Arc<AdminState>>) -> StatusCode {
+    info!("Reconnect requested through the admin API");
+    state.reconnect.notify_one();
+
+    StatusCode::ACCEPTED
+}
+
+async fn inject(State(state): State<Arc<AdminState>>, Json(injection): Json<Injection>) -> Result<StatusCode, AdminError> {
+    let queue = state.queue.upgrade()
+        .ok_or_else(|| AdminError(StatusCode::SERVICE_UNAVAILABLE, "Akari is shutting down".into()))?;
+
+    info!("Injecting happening through the admin API: {}", injection.line);
+
+    let id = injection.id.unwrap_or(0).to_string();
+    let context = telemetry::receive_span(&id);
+
+    queue.push(ServerEvent {
+        id,
+        time: injection.time.unwrap_or_else(now_timestamp).to_string(),
+        str: injection.line,
+        buckets: injection.regions.iter().map(|region| format!("region:{}", region)).collect(),
+        rmb_message: injection.rmb_message,
+        received_at: Some(now_timestamp_millis()),
+        context: context.clone(),
+    }).await;
+
+    context.span().end();
+
+    Ok(StatusCode::ACCEPTED)
+}
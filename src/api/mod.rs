@@ -0,0 +1,287 @@
+use std::{error::Error, sync::Arc};
+use axum::{
+    Json, Router, extract::{Path, Query, State}, http::StatusCode, response::{IntoResponse, Response}, routing::get
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder, Row, postgres::PgRow};
+
+use crate::{config::Config, events::ParsedEvent, output::{RMB_TABLE_NAME, events_table, parse_connect_options}, updates, world};
+
+const DEFAULT_BIND: &str = "127.0.0.1:8080";
+const DEFAULT_LIMIT: i64 = 100;
+const DEFAULT_MAX_LIMIT: i64 = 1000;
+
+struct ApiState {
+    pool: sqlx::PgPool,
+    table: &'static str,
+    max_limit: i64,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Deserialize, Default)]
+struct EventQuery {
+    // Comma-separated list of categories
+    category: Option<String>,
+    actor: Option<String>,
+    receptor: Option<String>,
+    origin: Option<String>,
+    destination: Option<String>,
+    // Time range (Unix timestamps, since is inclusive and until is exclusive)
+    since: Option<i64>,
+    until: Option<i64>,
+    // Event ID range (both exclusive)
+    after: Option<i64>,
+    before: Option<i64>,
+    // Last event ID of the previous page, as returned in next_cursor
+    cursor: Option<i64>,
+    limit: Option<i64>,
+    order: Option<SortOrder>,
+}
+
+#[derive(Serialize)]
+struct EventPage {
+    events: Vec<ParsedEvent>,
+    next_cursor: Option<i64>,
+}
+
+enum Subject {
+    Nation(String),
+    Region(String),
+}
+
+struct ApiError(sqlx::Error);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        error!("Query API database error: {}", self.0);
+
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "database error" }))
+        ).into_response()
+    }
+}
+
+pub async fn initialize(config: &Config) -> Result<(), Box<dyn Error>> {
+    let Some(api_config) = &config.api else {
+        return Ok(());
+    };
+
+    if !api_config.enabled { return Ok(()); }
+
+    // The API reads from the database of the Postgres output named in `output`, or of the first one
+    let postgres_config = config.output.postgres.as_ref().and_then(|instances| match &api_config.output {
+        Some(output) => instances.iter().find(|instance| instance.name.as_ref() == Some(output)),
+        None => instances.first(),
+    });
+
+    let options = parse_connect_options(postgres_config.and_then(|c| c.url.as_deref()))
+        .map_err(|err| format!("Error parsing database connection parameters: {}", err))?;
+
+    let pool = sqlx::PgPool::connect_with(options).await
+        .map_err(|err| format!("Error connecting to Postgres for the query API: {}", err))?;
+
+    let state = Arc::new(ApiState {
+        pool,
+        table: events_table(postgres_config),
+        max_limit: api_config.max_limit.unwrap_or(DEFAULT_MAX_LIMIT),
+    });
+
+    let router = Router::new()
+        .route("/events", get(list_events))
+        .route("/nations/{nation}/timeline", get(nation_timeline))
+        .route("/regions/{region}/timeline", get(region_timeline))
+        .route("/regions/{region}/updates", get(region_updates))
+        .route("/updates", get(update_status))
+        .route("/world", get(world_summary))
+        .route("/world/nations/{nation}", get(world_nation))
+        .route("/world/regions/{region}", get(world_region))
+        .with_state(state);
+
+    let bind = api_config.bind.as_deref().unwrap_or(DEFAULT_BIND);
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+
+    info!("Query API listening on {}", bind);
+
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, router).await {
+            error!("Query API server stopped: {}", err);
+        }
+    });
+
+    Ok(())
+}
+
+async fn list_events(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<EventPage>, ApiError> {
+    fetch_events(&state, &query, None).await.map(Json)
+}
+
+async fn nation_timeline(
+    State(state): State<Arc<ApiState>>,
+    Path(nation): Path<String>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<EventPage>, ApiError> {
+    fetch_events(&state, &query, Some(Subject::Nation(normalize_name(&nation)))).await.map(Json)
+}
+
+async fn region_timeline(
+    State(state): State<Arc<ApiState>>,
+    Path(region): Path<String>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<EventPage>, ApiError> {
+    fetch_events(&state, &query, Some(Subject::Region(normalize_name(&region)))).await.map(Json)
+}
+
+// Update times come from the update tracker, they are empty if [updates] isn't enabled
+async fn update_status() -> Json<updates::UpdateStatus> {
+    Json(updates::status())
+}
+
+async fn region_updates(Path(region): Path<String>) -> Response {
+    match updates::region(&normalize_name(&region)) {
+        Some(times) => Json(times).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "no update recorded for this region" }))).into_response(),
+    }
+}
+
+// The world state is empty if [world] isn't enabled
+async fn world_summary() -> Json<world::WorldSummary> {
+    Json(world::summary())
+}
+
+async fn world_nation(Path(nation): Path<String>) -> Response {
+    match world::nation(&normalize_name(&nation)) {
+        Some(nation) => Json(nation).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "unknown nation" }))).into_response(),
+    }
+}
+
+async fn world_region(Path(region): Path<String>) -> Response {
+    match world::region(&normalize_name(&region)) {
+        Some(region) => Json(region).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "unknown region" }))).into_response(),
+    }
+}
+
+async fn fetch_events(
+    state: &ApiState,
+    query: &EventQuery,
+    subject: Option<Subject>,
+) -> Result<EventPage, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, state.max_limit);
+    let mut builder = build_query(state.table, query, subject, limit);
+
+    let rows = builder.build().fetch_all(&state.pool).await.map_err(ApiError)?;
+    let events = rows.iter().map(row_to_event).collect::<Result<Vec<_>, _>>().map_err(ApiError)?;
+
+    let next_cursor = if events.len() as i64 == limit {
+        events.last().map(|event| event.event)
+    } else {
+        None
+    };
+
+    Ok(EventPage { events, next_cursor })
+}
+
+fn build_query(
+    table: &str,
+    query: &EventQuery,
+    subject: Option<Subject>,
+    limit: i64,
+) -> QueryBuilder<'static, Postgres> {
+    // RMB message bodies saved with `rmb_posts` are stored apart from the event, and put back in `data`
+    let mut builder = QueryBuilder::new(format!(
+        "SELECT e.event, e.time, e.actor, e.receptor, e.origin, e.destination, e.origin_inferred, e.category,
+            CASE WHEN r.message IS NULL THEN e.data ELSE e.data || r.message END AS data
+        FROM {} e LEFT JOIN {} r ON e.category = 'rmbpost' AND r.time = e.time AND r.event = e.event
+        WHERE TRUE", table, RMB_TABLE_NAME
+    ));
+
+    match subject {
+        Some(Subject::Nation(nation)) => {
+            builder.push(" AND (e.actor = ").push_bind(nation.clone())
+                .push(" OR e.receptor = ").push_bind(nation).push(")");
+        },
+        Some(Subject::Region(region)) => {
+            builder.push(" AND (e.origin = ").push_bind(region.clone())
+                .push(" OR e.destination = ").push_bind(region).push(")");
+        },
+        None => {}
+    }
+
+    if let Some(categories) = &query.category {
+        let categories: Vec<String> = categories.split(',').map(|c| c.trim().to_owned()).collect();
+        builder.push(" AND e.category = ANY(").push_bind(categories).push(")");
+    }
+
+    for (column, value) in [
+        ("actor", &query.actor),
+        ("receptor", &query.receptor),
+        ("origin", &query.origin),
+        ("destination", &query.destination),
+    ] {
+        if let Some(value) = value {
+            builder.push(format!(" AND e.{} = ", column)).push_bind(normalize_name(value));
+        }
+    }
+
+    if let Some(since) = query.since {
+        builder.push(" AND e.time >= ").push_bind(since);
+    }
+
+    if let Some(until) = query.until {
+        builder.push(" AND e.time < ").push_bind(until);
+    }
+
+    if let Some(after) = query.after {
+        builder.push(" AND e.event > ").push_bind(after);
+    }
+
+    if let Some(before) = query.before {
+        builder.push(" AND e.event < ").push_bind(before);
+    }
+
+    let order = query.order.unwrap_or_default();
+
+    if let Some(cursor) = query.cursor {
+        builder.push(if order == SortOrder::Asc { " AND e.event > " } else { " AND e.event < " }).push_bind(cursor);
+    }
+
+    builder.push(if order == SortOrder::Asc { " ORDER BY e.event ASC" } else { " ORDER BY e.event DESC" });
+    builder.push(" LIMIT ").push_bind(limit);
+
+    builder
+}
+
+fn row_to_event(row: &PgRow) -> Result<ParsedEvent, sqlx::Error> {
+    Ok(ParsedEvent {
+        event: row.try_get("event")?,
+        time: row.try_get::<i64, _>("time")? as u64,
+        actor: row.try_get("actor")?,
+        receptor: row.try_get("receptor")?,
+        origin: row.try_get("origin")?,
+        destination: row.try_get("destination")?,
+        origin_inferred: row.try_get("origin_inferred")?,
+        category: row.try_get("category")?,
+        data: row.try_get::<Option<Vec<String>>, _>("data")?.unwrap_or_default(),
+        received_at: None,
+        parsed_at: None,
+        emitted_at: None,
+    })
+}
+
+// NS names are stored in their canonical form (lowercase, underscores instead of spaces)
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "_")
+}
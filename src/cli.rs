@@ -0,0 +1,168 @@
+use std::{error::Error, fs::File, io::{self, BufRead, BufReader}};
+use clap::{Args, Parser, Subcommand};
+use opentelemetry::Context;
+use log::LevelFilter;
+
+use crate::{
+    config::load_config_with_outputs, events::ServerEvent, logging, output::{category_groups, known_categories},
+    parser::EventParser,
+};
+
+pub const CONFIG_PATH: &str = "config/akari.toml";
+
+#[derive(Parser)]
+#[command(version, about = "Reliable, standalone SSE client and event parser for NationStates")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Connect to NationStates and output events (default)
+    Run(RunArgs),
+    /// Validate the config file and list the resolved outputs and filters
+    CheckConfig(ConfigArgs),
+    /// Parse happening lines and print the resulting events as JSON
+    Parse(ParseArgs),
+    /// List every category Akari can emit
+    Categories(CategoriesArgs),
+}
+
+#[derive(Args, Default)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// Maximum log level (error, warn, info, debug or trace)
+    #[arg(long)]
+    pub log_level: Option<LevelFilter>,
+    /// Main nation name to identify with, overrides NS_USER_AGENT
+    #[arg(long)]
+    pub user_agent: Option<String>,
+    /// Run with the default configuration instead of reading a config file
+    #[arg(long, conflicts_with = "config")]
+    pub no_config: bool,
+    /// Reload the config file when it changes, in addition to on SIGHUP
+    #[arg(long, conflicts_with = "no_config")]
+    pub watch_config: bool,
+}
+
+#[derive(Args, Default)]
+pub struct ConfigArgs {
+    /// Path to the config file [default: config/akari.toml]
+    #[arg(long)]
+    pub config: Option<String>,
+}
+
+impl ConfigArgs {
+    pub fn path(&self) -> &str {
+        self.config.as_deref().unwrap_or(CONFIG_PATH)
+    }
+}
+
+#[derive(Args)]
+pub struct ParseArgs {
+    /// Happening text, e.g. "@@testlandia@@ relocated from %%lazarus%% to %%osiris%%."
+    #[arg(required_unless_present = "file")]
+    line: Option<String>,
+    /// Read happenings from a file instead, one per line ("-" for stdin). Lines can also be SSE event JSON.
+    #[arg(short, long, conflicts_with = "line")]
+    file: Option<String>,
+    /// Region used as the origin of happenings that don't mention it
+    #[arg(long)]
+    region: Option<String>,
+    /// Pretty-print the JSON output
+    #[arg(long)]
+    pretty: bool,
+}
+
+#[derive(Args)]
+pub struct CategoriesArgs {
+    /// List category groups and their members instead
+    #[arg(long)]
+    groups: bool,
+}
+
+pub fn check_config(args: &ConfigArgs) -> Result<(), Box<dyn Error>> {
+    // Shows warnings about the environment, such as ignored AKARI_* variables
+    logging::init(None)?;
+    let (config, outputs) = load_config_with_outputs(args.path())?;
+
+    println!("Config file '{}' is valid", args.path());
+    println!("Input: {} ({} workers)", config.input.url, config.input.workers);
+
+    for output in outputs {
+        println!();
+        println!("Output '{}'{}", output.name, if output.enabled { "" } else { " (disabled)" });
+
+        if let Some(include) = output.include {
+            println!("  include: {}", include.into_iter().collect::<Vec<_>>().join(", "));
+        }
+
+        if let Some(exclude) = output.exclude {
+            println!("  exclude: {}", exclude.into_iter().collect::<Vec<_>>().join(", "));
+        }
+
+        if let Some(filter) = output.filter {
+            println!("  filter: {}", filter);
+        }
+
+        if output.transform {
+            println!("  transform: yes");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn parse(args: &ParseArgs) -> Result<(), Box<dyn Error>> {
+    let parser = EventParser::new()?;
+
+    let lines: Box<dyn Iterator<Item = io::Result<String>>> = match (&args.line, args.file.as_deref()) {
+        (Some(line), _) => Box::new(std::iter::once(Ok(line.clone()))),
+        (None, Some("-")) => Box::new(io::stdin().lock().lines()),
+        (None, Some(path)) => Box::new(BufReader::new(File::open(path)?).lines()),
+        (None, None) => unreachable!("clap requires either a line or a file"),
+    };
+
+    for line in lines {
+        let line = line?;
+        let line = line.trim().strip_prefix("data: ").unwrap_or(line.trim());
+
+        if line.is_empty() { continue; }
+
+        let event = if line.starts_with('{') {
+            serde_json::from_str(line)?
+        } else {
+            ServerEvent {
+                id: "-1".into(),
+                time: "0".into(),
+                str: line.to_owned(),
+                buckets: args.region.iter().map(|region| format!("region:{}", region)).collect(),
+                rmb_message: None,
+                received_at: None,
+                context: Context::new(),
+            }
+        };
+
+        match parser.parse_server_event(event) {
+            Some(parsed) if args.pretty => println!("{}", serde_json::to_string_pretty(&parsed)?),
+            Some(parsed) => println!("{}", serde_json::to_string(&parsed)?),
+            None => eprintln!("Could not parse '{}'", line),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn categories(args: &CategoriesArgs) {
+    if args.groups {
+        for (group, members) in category_groups() {
+            println!("@{}: {}", group, members.join(", "));
+        }
+    } else {
+        for category in known_categories() {
+            println!("{}", category);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::{collections::HashMap, fs::read_to_string};
+use log::warn;
+use toml::{Table, Value};
+
+use crate::config::{Config, ConfigError};
+
+// AKARI_OUTPUT__POSTGRES__ENABLED overrides output.postgres.enabled
+const ENV_PREFIX: &str = "AKARI_";
+const ENV_SEPARATOR: &str = "__";
+// url_file = "/run/secrets/db_url" (or AKARI_..._URL_FILE) sets url to the contents of the file
+const FILE_SUFFIX: &str = "_file";
+
+// Applies ${VAR} interpolation, *_file secrets and AKARI_* overrides to a parsed config file
+pub fn apply_environment(table: &mut Table, vars: &HashMap<String, String>) -> Result<(), ConfigError> {
+    interpolate_table(table, "", vars)?;
+    read_secret_files(table, "")?;
+
+    let mut overrides: Vec<(&String, &String)> = vars.iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+
+    // Apply in a stable order, so array entries are created before their fields
+    overrides.sort();
+
+    let sections = config_sections();
+
+    for (name, value) in overrides {
+        // Other software uses the same prefix, e.g. Kubernetes sets AKARI_SERVICE_HOST for a Service named akari
+        match name[ENV_PREFIX.len()..].split_once(ENV_SEPARATOR) {
+            Some((section, _)) if sections.contains(&section.to_lowercase()) => apply_override(table, name, value)?,
+            _ => warn!("Ignoring environment variable '{}', it doesn't start with a config section", name),
+        }
+    }
+
+    Ok(())
+}
+
+// The top-level tables of the config file, [input], [output], ...
+fn config_sections() -> Vec<String> {
+    match serde_json::to_value(Config::default()) {
+        Ok(serde_json::Value::Object(sections)) => sections.into_iter().map(|(section, _)| section).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() { key.to_owned() } else { format!("{}.{}", prefix, key) }
+}
+
+fn interpolate_table(table: &mut Table, prefix: &str, vars: &HashMap<String, String>) -> Result<(), ConfigError> {
+    for (key, value) in table.iter_mut() {
+        interpolate_value(value, &join_key(prefix, key), vars)?;
+    }
+
+    Ok(())
+}
+
+fn interpolate_value(value: &mut Value, key: &str, vars: &HashMap<String, String>) -> Result<(), ConfigError> {
+    match value {
+        Value::String(string) => *string = interpolate(string, vars).map_err(|reason| ConfigError::new(key, reason))?,
+        Value::Array(array) => {
+            for (i, item) in array.iter_mut().enumerate() {
+                interpolate_value(item, &format!("{}[{}]", key, i), vars)?;
+            }
+        },
+        Value::Table(table) => interpolate_table(table, key, vars)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// Replaces ${VAR} and ${VAR:-default} with the value of VAR. $$ is a literal $.
+fn interpolate(string: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let mut result = String::with_capacity(string.len());
+    let mut rest = string;
+
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            result.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let Some(end) = after.find('}') else {
+                return Err(format!("unclosed '${{' in '{}'", string));
+            };
+
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+
+            match (vars.get(name), default) {
+                (Some(value), _) => result.push_str(value),
+                (None, Some(default)) => result.push_str(default),
+                (None, None) => return Err(format!("environment variable '{}' is not set", name)),
+            }
+
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+            rest = &rest[1..];
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn read_secret(path: &str) -> Result<String, String> {
+    read_to_string(path)
+        .map(|contents| contents.trim_end_matches(['\r', '\n']).to_owned())
+        .map_err(|err| format!("couldn't read secret file '{}': {}", path, err))
+}
+
+fn read_secret_files(table: &mut Table, prefix: &str) -> Result<(), ConfigError> {
+    let secret_keys: Vec<String> = table.keys().filter(|key| key.ends_with(FILE_SUFFIX)).cloned().collect();
+
+    for file_key in secret_keys {
+        let key = file_key.trim_end_matches(FILE_SUFFIX).to_owned();
+
+        let Some(Value::String(path)) = table.remove(&file_key) else {
+            return Err(ConfigError::new(join_key(prefix, &file_key), "must be the path of a file"));
+        };
+
+        if table.contains_key(&key) {
+            return Err(ConfigError::new(
+                join_key(prefix, &file_key), format!("can't be set together with '{}'", key)
+            ));
+        }
+
+        let secret = read_secret(&path).map_err(|reason| ConfigError::new(join_key(prefix, &file_key), reason))?;
+        table.insert(key, Value::String(secret));
+    }
+
+    for (key, value) in table.iter_mut() {
+        match value {
+            Value::Table(inner) => read_secret_files(inner, &join_key(prefix, key))?,
+            Value::Array(array) => {
+                for (i, item) in array.iter_mut().enumerate() {
+                    if let Value::Table(inner) = item {
+                        read_secret_files(inner, &format!("{}[{}]", join_key(prefix, key), i))?;
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn apply_override(table: &mut Table, name: &str, raw: &str) -> Result<(), ConfigError> {
+    let mut path: Vec<String> = name[ENV_PREFIX.len()..]
+        .split(ENV_SEPARATOR)
+        .map(|segment| segment.to_lowercase())
+        .collect();
+
+    if path.iter().any(|segment| segment.is_empty()) {
+        return Err(ConfigError::new(name, "is not a valid config key"));
+    }
+
+    let last = path.pop().expect("split always returns at least one segment");
+
+    let mut current = table;
+    let mut segments = path.iter();
+
+    while let Some(segment) = segments.next() {
+        let entry = current.entry(segment.clone()).or_insert_with(|| Value::Table(Table::new()));
+
+        // Instances in an array of tables are addressed by index, e.g. AKARI_OUTPUT__FILE__1__PATH
+        let entry = match entry {
+            Value::Array(array) => {
+                let index = segments.next().and_then(|index| index.parse::<usize>().ok()).ok_or_else(|| {
+                    ConfigError::new(name, format!("'{}' is a list, expected an index after it", segment))
+                })?;
+
+                let entries = array.len();
+                array.get_mut(index).ok_or_else(|| {
+                    ConfigError::new(name, format!("'{}' only has {} entries", segment, entries))
+                })?
+            },
+            entry => entry,
+        };
+
+        current = match entry {
+            Value::Table(inner) => inner,
+            _ => return Err(ConfigError::new(name, format!("'{}' is not a table", segment))),
+        };
+    }
+
+    let (last, value) = match last.strip_suffix(FILE_SUFFIX) {
+        Some(key) => (key.to_owned(), Value::String(read_secret(raw).map_err(|reason| ConfigError::new(name, reason))?)),
+        None => {
+            let value = parse_value(raw, current.get(&last)).map_err(|reason| ConfigError::new(name, reason))?;
+            (last, value)
+        },
+    };
+
+    current.insert(last, value);
+    Ok(())
+}
+
+// Values of keys that are in the file are read as the same type, so PASSWORD=12345 stays a string.
+// Other values are read as TOML when possible (true, 5, ["a", "b"], "12345") and as strings otherwise.
+fn parse_value(raw: &str, existing: Option<&Value>) -> Result<Value, String> {
+    let invalid = |expected: &str| format!("'{}' is not {}", raw, expected);
+
+    match existing {
+        Some(Value::String(_)) => Ok(Value::String(raw.to_owned())),
+        Some(Value::Integer(_)) => raw.parse().map(Value::Integer).map_err(|_| invalid("an integer")),
+        Some(Value::Float(_)) => raw.parse().map(Value::Float).map_err(|_| invalid("a number")),
+        Some(Value::Boolean(_)) => raw.parse().map(Value::Boolean).map_err(|_| invalid("true or false")),
+        _ => Ok(toml::from_str::<Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| Value::String(raw.to_owned()))),
+    }
+}
@@ -1,7 +1,8 @@
+use opentelemetry::Context;
 use serde::{Deserialize, Serialize};
 use std::{sync::atomic::{AtomicUsize, Ordering}, time::{SystemTime, UNIX_EPOCH}};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct ServerEvent {
     pub id: String,
     pub time: String,
@@ -9,9 +10,22 @@ pub struct ServerEvent {
     pub buckets: Vec<String>,
     #[serde(rename = "rmbMessage")]
     pub rmb_message: Option<String>,
+    // Set by Akari when the SSE frame arrived, in milliseconds
+    pub received_at: Option<u64>,
     // pub htmlStr: String
+    // Trace of the event through the pipeline, not kept in the spill file
+    #[serde(skip)]
+    pub context: Context,
 }
 
+// Origin of events that should have a region bucket but don't
+pub const UNKNOWN_ORIGIN: &str = "[unknown]";
+
+// Categories of the events emitted by Akari itself, see the SystemEvent constructors below
+pub const SYSTEM_CATEGORIES: &[&str] = &[
+    "conninit", "conndrop", "connmiss", "shutdown", "configreload", "backpressure", "updstart", "updprog", "updend",
+];
+
 #[derive(Debug)]
 pub struct SystemEvent {
     pub time: u64,
@@ -19,39 +33,105 @@ pub struct SystemEvent {
     pub data: Vec<String>
 }
 
-fn now_timestamp() -> u64 {
+pub fn now_timestamp() -> u64 {
     SystemTime::now().duration_since(UNIX_EPOCH).expect(
         "Current system time should be later than the Unix epoch"
     ).as_secs()
 }
 
+pub fn now_timestamp_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect(
+        "Current system time should be later than the Unix epoch"
+    ).as_millis() as u64
+}
+
 impl SystemEvent {
-    pub fn connection_initialized() -> SequencedEvent {
-        SequencedEvent::wrap_system(SystemEvent {
+    pub fn connection_initialized() -> SystemEvent {
+        SystemEvent {
             time: now_timestamp(),
             category: "conninit",
             data: vec![]
-        })
+        }
     }
 
-    pub fn connection_dropped(last_event_id: i64) -> SequencedEvent {
-        SequencedEvent::wrap_system(SystemEvent {
+    pub fn connection_dropped(last_event_id: i64) -> SystemEvent {
+        SystemEvent {
             time: now_timestamp(),
             category: "conndrop",
             data: vec![last_event_id.to_string()]
-        })
+        }
     }
 
     pub fn events_missed(
         events_missed: i64,
         last_event_id: i64,
         current_id: i64,
-    ) -> SequencedEvent {
-        SequencedEvent::wrap_system(SystemEvent {
+    ) -> SystemEvent {
+        SystemEvent {
             time: now_timestamp(),
             category: "connmiss",
             data: vec![events_missed.to_string(), last_event_id.to_string(), current_id.to_string()]
-        })
+        }
+    }
+
+    pub fn config_reloaded(added: &[String], removed: &[String], changed: &[String]) -> SystemEvent {
+        SystemEvent {
+            time: now_timestamp(),
+            category: "configreload",
+            data: vec![added.join(","), removed.join(","), changed.join(",")]
+        }
+    }
+
+    pub fn shutdown(last_event_id: i64) -> SystemEvent {
+        SystemEvent {
+            time: now_timestamp(),
+            category: "shutdown",
+            data: vec![last_event_id.to_string()]
+        }
+    }
+
+    // Sent with "on" when the queue fills up and "off" once it has room again,
+    // along with the number of events that were dropped or spilled in between
+    pub fn backpressure(active: bool, policy: &str, affected: u64) -> SystemEvent {
+        SystemEvent {
+            time: now_timestamp(),
+            category: "backpressure",
+            data: vec![if active { "on" } else { "off" }.to_owned(), policy.to_owned(), affected.to_string()]
+        }
+    }
+
+    // Update events are timed with the NS time of the first and last rupdate happenings
+    pub fn update_started(time: u64, kind: &str) -> SystemEvent {
+        SystemEvent {
+            time,
+            category: "updstart",
+            data: vec![kind.to_owned()]
+        }
+    }
+
+    // Expected is the number of regions in the previous update of the same kind, 0 if unknown
+    pub fn update_progress(kind: &str, regions: u64, expected: u64, elapsed: u64) -> SystemEvent {
+        SystemEvent {
+            time: now_timestamp(),
+            category: "updprog",
+            data: vec![kind.to_owned(), regions.to_string(), expected.to_string(), elapsed.to_string()]
+        }
+    }
+
+    pub fn update_ended(time: u64, kind: &str, regions: u64, duration: u64) -> SystemEvent {
+        SystemEvent {
+            time,
+            category: "updend",
+            data: vec![kind.to_owned(), regions.to_string(), duration.to_string()]
+        }
+    }
+}
+
+impl From<SystemEvent> for ParsedEvent {
+    fn from(event: SystemEvent) -> Self {
+        let mut result = ParsedEvent::new(-1, event.time, event.category);
+        result.data = event.data;
+        result
     }
 }
 
@@ -103,9 +183,19 @@ pub struct ParsedEvent {
     pub origin: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub destination: Option<String>,
+    // Set when the origin was missing from the happening and filled in from the world state
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub origin_inferred: bool,
     pub category: String,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub data: Vec<String>,
+    // Latency fields, Unix timestamps in milliseconds. Only output with `latency = true` in the transform.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parsed_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emitted_at: Option<u64>,
 }
 
 impl ParsedEvent {
@@ -121,8 +211,12 @@ impl ParsedEvent {
             receptor: None, 
             origin: None, 
             destination: None, 
+            origin_inferred: false,
             category: category.to_owned(), 
-            data: Vec::new() 
+            data: Vec::new(),
+            received_at: None,
+            parsed_at: None,
+            emitted_at: None,
         }
     }
 }
\ No newline at end of file
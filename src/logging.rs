@@ -0,0 +1,231 @@
+use file_rotate::{ContentLimit, FileRotate, compression::Compression, suffix::{AppendTimestamp, FileLimit}};
+use lazy_static::lazy_static;
+use log::{LevelFilter, Log, Metadata, Record, STATIC_MAX_LEVEL, kv::{self, Key, VisitSource}};
+use std::{error::Error, fs, io::{self, IsTerminal, Write}, path::Path, sync::{Mutex, RwLock}};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+struct LogFile {
+    writer: Mutex<FileRotate<AppendTimestamp>>,
+    format: LogFormat,
+}
+
+struct Settings {
+    level: LevelFilter,
+    // Longest module path first, so the most specific setting wins
+    modules: Vec<(String, LevelFilter)>,
+    format: LogFormat,
+    color: bool,
+    file: Option<LogFile>,
+}
+
+impl Settings {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules.iter()
+            .find(|(module, _)| target == module || target.strip_prefix(module.as_str()).is_some_and(|rest| rest.starts_with("::")))
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).chain([self.level]).max().unwrap_or(self.level)
+    }
+}
+
+struct Logger {
+    settings: RwLock<Settings>,
+    // Set with --log-level, takes priority over the config file
+    level_override: RwLock<Option<LevelFilter>>,
+}
+
+lazy_static! {
+    static ref LOGGER: Logger = Logger {
+        settings: RwLock::new(Settings {
+            level: STATIC_MAX_LEVEL,
+            modules: Vec::new(),
+            format: LogFormat::Text,
+            color: io::stderr().is_terminal(),
+            file: None,
+        }),
+        level_override: RwLock::new(None),
+    };
+}
+
+// Logs to stderr in the text format until the config file has been read
+pub fn init(level_override: Option<LevelFilter>) -> Result<(), Box<dyn Error>> {
+    *LOGGER.level_override.write().unwrap() = level_override;
+    LOGGER.settings.write().unwrap().level = level_override.unwrap_or(STATIC_MAX_LEVEL);
+
+    log::set_logger(&*LOGGER)?;
+    log::set_max_level(level_override.unwrap_or(STATIC_MAX_LEVEL).min(STATIC_MAX_LEVEL));
+
+    Ok(())
+}
+
+// Applies the [logging] section, on startup and on every reload
+pub fn configure(config: Option<&LoggingConfig>) -> Result<(), Box<dyn Error>> {
+    let format = config.and_then(|c| c.format).unwrap_or_default();
+
+    let file = config.and_then(|c| c.file.as_ref()).map(|file| {
+        // FileRotate panics if it can't create the directory itself
+        if let Some(parent) = Path::new(&file.path).parent() && !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("Failed to create log directory '{}': {}", parent.display(), err))?;
+        }
+
+        Ok::<_, Box<dyn Error>>(LogFile {
+            writer: Mutex::new(FileRotate::new(
+                &file.path,
+                AppendTimestamp::default(file.maxfiles.map_or(FileLimit::Unlimited, FileLimit::MaxFiles)),
+                ContentLimit::Lines(file.threshold.map_or(500, |v| v * 1000)),
+                Compression::OnRotate(0),
+                None,
+            )),
+            format: file.format.unwrap_or(format),
+        })
+    }).transpose()?;
+
+    // Module paths can be given relative to the crate, e.g. "net::conn" for "akari::net::conn"
+    let mut modules: Vec<(String, LevelFilter)> = config.and_then(|c| c.modules.as_ref()).into_iter()
+        .flat_map(|modules| modules.iter())
+        .flat_map(|(module, level)| [(module.clone(), *level), (format!("akari::{}", module), *level)])
+        .collect();
+
+    modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+
+    let level = LOGGER.level_override.read().unwrap()
+        .or(config.and_then(|c| c.level))
+        .unwrap_or(STATIC_MAX_LEVEL);
+
+    let settings = Settings { level, modules, format, color: io::stderr().is_terminal(), file };
+    log::set_max_level(settings.max_level().min(STATIC_MAX_LEVEL));
+    *LOGGER.settings.write().unwrap() = settings;
+
+    Ok(())
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.settings.read().unwrap().level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        let settings = self.settings.read().unwrap();
+
+        if record.level() > settings.level_for(record.target()) {
+            return;
+        }
+
+        let now = OffsetDateTime::now_utc();
+
+        // Logging has nowhere to report its own errors, so write failures are ignored
+        let _ = writeln!(io::stderr().lock(), "{}", format_record(record, settings.format, settings.color, now));
+
+        if let Some(file) = &settings.file {
+            let _ = writeln!(file.writer.lock().unwrap(), "{}", format_record(record, file.format, false, now));
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.settings.read().unwrap().file {
+            let _ = file.writer.lock().unwrap().flush();
+        }
+    }
+}
+
+fn format_record(record: &Record, format: LogFormat, color: bool, now: OffsetDateTime) -> String {
+    match format {
+        LogFormat::Text => format_text(record, color, now),
+        LogFormat::Json => format_json(record, now),
+    }
+}
+
+// 12:34:56 [INFO] message, with the module path for messages from other crates
+fn format_text(record: &Record, color: bool, now: OffsetDateTime) -> String {
+    let level = if color {
+        let code = match record.level() {
+            log::Level::Error => 31,
+            log::Level::Warn => 33,
+            log::Level::Info => 34,
+            log::Level::Debug => 36,
+            log::Level::Trace => 37,
+        };
+
+        format!("\x1b[{}m[{}]\x1b[0m", code, record.level())
+    } else {
+        format!("[{}]", record.level())
+    };
+
+    let time = format!("{:02}:{:02}:{:02}", now.hour(), now.minute(), now.second());
+
+    if record.target() == "akari" || record.target().starts_with("akari::") {
+        format!("{} {} {}", time, level, record.args())
+    } else {
+        format!("{} {} {}: {}", time, level, record.target(), record.args())
+    }
+}
+
+// {"time": "...", "level": "WARN", "target": "akari::output", "message": "...", "output": "file", ...}
+fn format_json(record: &Record, now: OffsetDateTime) -> String {
+    let mut entry = serde_json::Map::new();
+
+    entry.insert("time".into(), now.format(&Rfc3339).unwrap_or_default().into());
+    entry.insert("level".into(), record.level().as_str().into());
+    entry.insert("target".into(), record.target().into());
+    entry.insert("message".into(), record.args().to_string().into());
+
+    let _ = record.key_values().visit(&mut FieldVisitor(&mut entry));
+
+    serde_json::Value::Object(entry).to_string()
+}
+
+// Copies the structured fields of a log call (e.g. `warn!(output = name; "...")`) into the JSON entry
+struct FieldVisitor<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for FieldVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.insert(key.to_string(), serde_json::to_value(&value).unwrap_or_default());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_levels() {
+        let settings = Settings {
+            level: LevelFilter::Info,
+            modules: vec![
+                ("akari::net::conn".into(), LevelFilter::Debug),
+                ("akari::net".into(), LevelFilter::Warn),
+                ("sqlx".into(), LevelFilter::Error),
+            ],
+            format: LogFormat::Text,
+            color: false,
+            file: None,
+        };
+
+        assert_eq!(settings.level_for("akari::net::conn"), LevelFilter::Debug);
+        assert_eq!(settings.level_for("akari::net::backoff"), LevelFilter::Warn);
+        assert_eq!(settings.level_for("akari::network"), LevelFilter::Info);
+        assert_eq!(settings.level_for("sqlx::query"), LevelFilter::Error);
+        assert_eq!(settings.max_level(), LevelFilter::Debug);
+    }
+
+    #[test]
+    fn test_json_format() {
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let kvs = [("output", kv::Value::from("file")), ("event", kv::Value::from(42))];
+
+        let line = format_json(&Record::builder()
+            .args(format_args!("Failed to write"))
+            .level(log::Level::Warn)
+            .target("akari::output")
+            .key_values(&kvs)
+            .build(), now);
+
+        assert_eq!(line, r#"{"time":"1970-01-01T00:00:00Z","level":"WARN","target":"akari::output","message":"Failed to write","output":"file","event":42}"#);
+    }
+}
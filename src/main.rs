@@ -5,92 +5,201 @@ mod config;
 mod output;
 mod worker;
 mod unicode;
+mod api;
+mod monitoring;
+mod reload;
+mod cli;
+mod queue;
+mod logging;
+mod admin;
+mod telemetry;
+mod updates;
+mod world;
 
-use std::{env, process::exit, error::Error, time::Instant};
-use config_file::FromConfigFile;
-use crossbeam::channel::Sender;
-use log::{info, warn, error, STATIC_MAX_LEVEL};
-use simplelog::{Config as LogConfig, TermLogger, TerminalMode, ColorChoice};
+use std::{env, process::exit, error::Error, sync::Arc, time::{Duration, Instant}};
+use clap::Parser;
+use log::{info, warn, error};
+use opentelemetry::trace::TraceContextExt;
+use tokio::{signal::unix::{signal, SignalKind}, sync::Notify};
 
-use crate::config::Config;
+use crate::cli::{Cli, Command, RunArgs};
+use crate::config::{Config, load_config};
 use crate::net::{Connection, MessageResult, ExponentialBackoff};
 use crate::output::initialize_outputs;
-use crate::worker::spawn_work_threads;
-use crate::events::{SystemEvent, SequencedEvent};
+use crate::reload::ConfigWatcher;
+use crate::worker::{Workers, spawn_workers};
+use crate::events::SystemEvent;
+use crate::queue::EventQueue;
+use crate::monitoring::{health, metrics};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
-const CONFIG_PATH: &str = "config/akari.toml";
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 10;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    TermLogger::init(
-        STATIC_MAX_LEVEL, LogConfig::default(), TerminalMode::Stderr, ColorChoice::Auto
-    )?;
+    let result = match Cli::parse().command.unwrap_or(Command::Run(RunArgs::default())) {
+        Command::Run(args) => run(args).await,
+        Command::CheckConfig(args) => cli::check_config(&args),
+        Command::Parse(args) => cli::parse(&args),
+        Command::Categories(args) => {
+            cli::categories(&args);
+            Ok(())
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        exit(1);
+    }
+
+    Ok(())
+}
+
+async fn run(args: RunArgs) -> Result<(), Box<dyn Error>> {
+    logging::init(args.log_level)?;
 
     dotenv::dotenv().ok();
 
-    let user_agent = read_user_agent();
+    let user_agent = read_user_agent(args.user_agent.clone());
     info!("Running with user agent '{}'", user_agent);
 
-    let config = read_config();
+    let config_path = args.config.path();
+    let config = read_config(&args);
+    logging::configure(config.logging.as_ref())?;
+    let tracer_provider = telemetry::initialize(config.tracing.as_ref())?;
     let outputs = initialize_outputs(&config).await?;
-    let sender = spawn_work_threads(outputs, config.input.workers);
+    api::initialize(&config).await?;
+    monitoring::initialize(&config).await?;
+    let workers = spawn_workers(outputs, &config).await?;
+    let reconnect = Arc::new(Notify::new());
+    admin::initialize(&config, &workers, reconnect.clone()).await?;
+    let shutdown_timeout = config.shutdown.as_ref().and_then(|c| c.timeout).unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
     let mut backoff = ExponentialBackoff::new(&[60, 120, 240, 960, 1800]);
+    let mut last_event_id: Option<i64> = None;
+    let mut current_config = config.clone();
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+    let mut watcher = if args.watch_config { Some(ConfigWatcher::new(config_path).await) } else { None };
 
-    tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
-            info!("Shutting down...");
-        },
-        _ = main_loop(&user_agent, config, sender, &mut backoff) => {},
+    {
+        let input = main_loop(&user_agent, config, workers.events.clone(), &reconnect, &mut backoff, &mut last_event_id);
+        let shutdown = shutdown_signal();
+        tokio::pin!(input, shutdown);
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => {
+                    info!("Shutting down...");
+                    break;
+                },
+                _ = hangup.recv() => {
+                    info!("Received SIGHUP, reloading configuration from '{}'", config_path);
+
+                    if let Err(err) = reload::reload_config(config_path, &mut current_config, &workers).await {
+                        error!(error_kind = "config"; "Failed to reload configuration, keeping the current one: {}", err);
+                    }
+                },
+                _ = ConfigWatcher::changed(&mut watcher) => {
+                    info!("Configuration file '{}' changed, reloading", config_path);
+
+                    if let Err(err) = reload::reload_config(config_path, &mut current_config, &workers).await {
+                        error!(error_kind = "config"; "Failed to reload configuration, keeping the current one: {}", err);
+                    }
+                },
+                _ = &mut input => break,
+            }
+        }
+    }
+
+    let Workers { events: queue, output_task, .. } = workers;
+
+    // Input has stopped at this point, let the workers output everything that was already received
+    let drain = async move {
+        queue.drain().await;
+        queue.push_system(SystemEvent::shutdown(last_event_id.unwrap_or(-1)));
+        drop(queue);
+
+        output_task.await
+    };
+
+    if tokio::time::timeout(Duration::from_secs(shutdown_timeout), drain).await.is_err() {
+        error!("Outputs did not finish within {} seconds, exiting with events still queued", shutdown_timeout);
+        exit(1);
+    }
+
+    info!("All events have been output");
+
+    if let Some(provider) = tracer_provider {
+        telemetry::shutdown(provider).await;
     }
 
     Ok(())
 }
 
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate.recv() => {},
+    }
+}
+
 async fn main_loop(
     user_agent: &String,
     config: Config,
-    sender: Sender<SequencedEvent>,
+    queue: EventQueue,
+    reconnect: &Notify,
     backoff: &mut ExponentialBackoff<'_>,
+    last_event_id: &mut Option<i64>,
 ) -> Result<(), Box<dyn Error>> {
-    let mut last_event_id: Option<i64> = None;
-
     loop {
-        let mut connection = Connection::connect(&config.input.url, user_agent, backoff, last_event_id).await;
+        health::set_connected(false);
+        let mut connection = Connection::connect(&config.input.url, user_agent, backoff, *last_event_id).await;
+        health::set_connected(true);
 
-        sender.send(SystemEvent::connection_initialized()).unwrap_or_else(|err| {
-            error!("Failed to send system event to worker: {err}");
-        });
+        queue.push_system(SystemEvent::connection_initialized());
 
         let mut last_event_time = Instant::now();
 
         loop {
-            match connection.read_messages().await? {
+            let result = tokio::select! {
+                result = connection.read_messages() => result?,
+                _ = reconnect.notified() => {
+                    info!("Dropping connection and reconnecting");
+                    break;
+                },
+            };
+
+            match result {
                 MessageResult::Messages(messages) => {
                     for event in messages {
                         let current_id: i64 = event.id.parse().unwrap_or(-1);
 
-                        if Some(current_id) == last_event_id {
+                        if Some(current_id) == *last_event_id {
                             continue;
                         }
 
-                        if let Some((last_id, events_missed)) = detect_missed_events(last_event_id, current_id) {
-                            sender.send(SystemEvent::events_missed(
-                                events_missed, last_id, current_id
-                            )).unwrap_or_else(|err| {
-                                error!("Failed to send system event to worker: {err}");
-                            });
+                        metrics::EVENTS_RECEIVED.inc();
+                        health::event_received();
+
+                        if let Some((last_id, events_missed)) = detect_missed_events(*last_event_id, current_id) {
+                            metrics::MISSED_EVENTS.inc_by(events_missed as u64);
+
+                            queue.push_system(SystemEvent::events_missed(events_missed, last_id, current_id));
                         }
 
-                        sender.send(SequencedEvent::wrap_server(event)).unwrap_or_else(|err| {
-                            error!("Failed to send server event to worker: {err}");
-                        });
+                        // The receive span covers the time spent waiting for room in the queue
+                        let context = event.context.clone();
+                        queue.push(event).await;
+                        context.span().end();
 
                         last_event_time = Instant::now();
-                        last_event_id = Some(current_id);
+                        *last_event_id = Some(current_id);
                     }
                 },
                 MessageResult::NoMessages => {
+                    queue.refill();
+
                     let elapsed = Instant::now().duration_since(last_event_time);
                     if elapsed.as_secs() > 30 {
                         warn!("No events in the last 30 seconds, dropping connection and reconnecting");
@@ -105,22 +214,20 @@ async fn main_loop(
 
         drop(connection);
 
-        sender.send(
-            SystemEvent::connection_dropped(last_event_id.unwrap_or(-1))
-        ).unwrap_or_else(|err| {
-            error!("Failed to send system event to worker: {err}");
-        });
+        queue.push_system(SystemEvent::connection_dropped(last_event_id.unwrap_or(-1)));
 
         info!("Attempting to reconnect");
+        metrics::RECONNECTS.inc();
     }
 }
 
-fn read_user_agent() -> String {
-    let user = match env::var("NS_USER_AGENT") {
+// A user agent given on the command line takes priority over the environment
+fn read_user_agent(user_agent: Option<String>) -> String {
+    let user = match user_agent.map_or_else(|| env::var("NS_USER_AGENT"), Ok) {
         Ok(user) => user,
         Err(err) => match err {
             env::VarError::NotPresent => {
-                error!("No user agent provided, please set the NS_USER_AGENT environment variable to your main nation name");
+                error!("No user agent provided, please set the NS_USER_AGENT environment variable or --user-agent to your main nation name");
                 exit(1);
             },
             env::VarError::NotUnicode(_) => {
@@ -133,10 +240,15 @@ fn read_user_agent() -> String {
     format!("Akari/{} by Merethin, in use by {}", VERSION, user)
 }
 
-fn read_config() -> Config {
-    Config::from_config_file(CONFIG_PATH).unwrap_or_else(|e| {
-        warn!("Failed to load config file: {} - loading default values", e);
-        Config::default()
+fn read_config(args: &RunArgs) -> Config {
+    if args.no_config {
+        warn!("Running without a config file, using the default configuration");
+        return Config::default();
+    }
+
+    load_config(args.config.path()).unwrap_or_else(|err| {
+        error!(error_kind = "config"; "Invalid configuration: {}", err);
+        exit(1);
     })
 }
 
@@ -145,6 +257,7 @@ fn detect_missed_events(last_event_id: Option<i64>, current_id: i64) -> Option<(
         let difference = current_id - (last_id + 1);
         if difference > 0 {
             warn!(
+                missed = difference, last_event = last_id, event = current_id, error_kind = "connection";
                 "Missed {} NationStates events (from {} to {})",
                 difference, last_id, current_id
             );
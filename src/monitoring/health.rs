@@ -0,0 +1,209 @@
+use std::{
+    collections::BTreeMap, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}}
+};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::{config::HealthConfig, events::now_timestamp};
+
+const DEFAULT_MAX_EVENT_AGE: u64 = 60;
+const DEFAULT_MAX_DISCONNECTED: u64 = 600;
+const DEFAULT_MAX_OUTPUT_FAILURES: u64 = 5;
+const DEFAULT_MAX_BUSY: u64 = 120;
+
+struct HealthState {
+    connected: AtomicBool,
+    // Time of the last connection or disconnection
+    connection_changed: AtomicU64,
+    last_event: AtomicU64,
+    outputs: Mutex<BTreeMap<String, OutputHealth>>,
+    workers: Mutex<Vec<Arc<WorkerHeartbeat>>>,
+}
+
+#[derive(Clone, Default, Serialize)]
+struct OutputHealth {
+    consecutive_failures: u64,
+    last_success: Option<u64>,
+    last_failure: Option<u64>,
+}
+
+struct WorkerHeartbeat {
+    name: String,
+    alive: AtomicBool,
+    // Time the worker started handling its current event, 0 if idle
+    busy_since: AtomicU64,
+}
+
+lazy_static! {
+    static ref STATE: HealthState = HealthState {
+        connected: AtomicBool::new(false),
+        connection_changed: AtomicU64::new(now_timestamp()),
+        last_event: AtomicU64::new(0),
+        outputs: Mutex::new(BTreeMap::new()),
+        workers: Mutex::new(Vec::new()),
+    };
+}
+
+pub fn set_connected(connected: bool) {
+    STATE.connected.store(connected, Ordering::Relaxed);
+    STATE.connection_changed.store(now_timestamp(), Ordering::Relaxed);
+}
+
+pub fn event_received() {
+    STATE.last_event.store(now_timestamp(), Ordering::Relaxed);
+}
+
+pub fn record_output(name: &str, success: bool) {
+    let mut outputs = STATE.outputs.lock().unwrap();
+    let output = outputs.entry(name.to_owned()).or_default();
+
+    if success {
+        output.consecutive_failures = 0;
+        output.last_success = Some(now_timestamp());
+    } else {
+        output.consecutive_failures += 1;
+        output.last_failure = Some(now_timestamp());
+    }
+}
+
+// Called when a reload removes an output, so its last failures don't keep the service unready
+pub fn remove_output(name: &str) {
+    STATE.outputs.lock().unwrap().remove(name);
+}
+
+pub fn register_worker(name: &str) -> WorkerGuard {
+    let heartbeat = Arc::new(WorkerHeartbeat {
+        name: name.to_owned(),
+        alive: AtomicBool::new(true),
+        busy_since: AtomicU64::new(0),
+    });
+
+    STATE.workers.lock().unwrap().push(heartbeat.clone());
+    WorkerGuard { heartbeat }
+}
+
+// Held by each worker thread, so the worker is marked as dead if the thread exits or panics
+pub struct WorkerGuard {
+    heartbeat: Arc<WorkerHeartbeat>,
+}
+
+impl WorkerGuard {
+    pub fn busy(&self) {
+        self.heartbeat.busy_since.store(now_timestamp(), Ordering::Relaxed);
+    }
+
+    pub fn idle(&self) {
+        self.heartbeat.busy_since.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        self.heartbeat.alive.store(false, Ordering::Relaxed);
+    }
+}
+
+#[derive(Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    connection: ConnectionReport,
+    outputs: BTreeMap<String, OutputReport>,
+    workers: BTreeMap<String, WorkerReport>,
+}
+
+#[derive(Serialize)]
+struct ConnectionReport {
+    connected: bool,
+    seconds_since_change: u64,
+    seconds_since_last_event: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct OutputReport {
+    healthy: bool,
+    #[serde(flatten)]
+    state: OutputHealth,
+}
+
+#[derive(Serialize)]
+struct WorkerReport {
+    alive: bool,
+    busy_seconds: Option<u64>,
+}
+
+pub struct HealthThresholds {
+    max_event_age: u64,
+    max_disconnected: u64,
+    max_output_failures: u64,
+    max_busy: u64,
+}
+
+impl HealthThresholds {
+    pub fn new(config: Option<&HealthConfig>) -> Self {
+        Self {
+            max_event_age: config.and_then(|c| c.max_event_age).unwrap_or(DEFAULT_MAX_EVENT_AGE),
+            max_disconnected: config.and_then(|c| c.max_disconnected).unwrap_or(DEFAULT_MAX_DISCONNECTED),
+            max_output_failures: config.and_then(|c| c.max_output_failures).unwrap_or(DEFAULT_MAX_OUTPUT_FAILURES),
+            max_busy: config.and_then(|c| c.max_busy).unwrap_or(DEFAULT_MAX_BUSY),
+        }
+    }
+}
+
+// Liveness: worker threads are running and not stuck, and the connection hasn't been down for too long.
+// Akari can't recover from these without a restart.
+pub fn liveness(thresholds: &HealthThresholds) -> HealthReport {
+    let mut report = build_report(thresholds);
+
+    let disconnected_too_long = !report.connection.connected
+        && report.connection.seconds_since_change > thresholds.max_disconnected;
+
+    report.healthy = report.workers.values().all(|worker| {
+        worker.alive && worker.busy_seconds.is_none_or(|busy| busy <= thresholds.max_busy)
+    }) && !disconnected_too_long;
+
+    report
+}
+
+// Readiness: connected to NationStates, receiving events and able to write to every output
+pub fn readiness(thresholds: &HealthThresholds) -> HealthReport {
+    let mut report = liveness(thresholds);
+
+    let receiving_events = report.connection.seconds_since_last_event
+        .is_some_and(|age| age <= thresholds.max_event_age);
+
+    report.healthy = report.healthy
+        && report.connection.connected
+        && receiving_events
+        && report.outputs.values().all(|output| output.healthy);
+
+    report
+}
+
+fn build_report(thresholds: &HealthThresholds) -> HealthReport {
+    let now = now_timestamp();
+    let last_event = STATE.last_event.load(Ordering::Relaxed);
+
+    let connection = ConnectionReport {
+        connected: STATE.connected.load(Ordering::Relaxed),
+        seconds_since_change: now.saturating_sub(STATE.connection_changed.load(Ordering::Relaxed)),
+        seconds_since_last_event: (last_event != 0).then(|| now.saturating_sub(last_event)),
+    };
+
+    let outputs = STATE.outputs.lock().unwrap().iter().map(|(name, state)| {
+        (name.clone(), OutputReport {
+            healthy: state.consecutive_failures < thresholds.max_output_failures,
+            state: state.clone(),
+        })
+    }).collect();
+
+    let workers = STATE.workers.lock().unwrap().iter().map(|worker| {
+        let busy_since = worker.busy_since.load(Ordering::Relaxed);
+
+        (worker.name.clone(), WorkerReport {
+            alive: worker.alive.load(Ordering::Relaxed),
+            busy_seconds: (busy_since != 0).then(|| now.saturating_sub(busy_since)),
+        })
+    }).collect();
+
+    HealthReport { healthy: true, connection, outputs, workers }
+}
@@ -0,0 +1,72 @@
+use lazy_static::lazy_static;
+use log::error;
+use prometheus::{
+    Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+    exponential_buckets, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+};
+
+lazy_static! {
+    pub static ref EVENTS_RECEIVED: IntCounter = register_int_counter!(
+        "akari_events_received_total", "Events received from the NationStates SSE feed"
+    ).unwrap();
+
+    // Unknown and skipped happenings show up here under the "unknown" and "skipped" categories
+    pub static ref EVENTS_PARSED: IntCounterVec = register_int_counter_vec!(
+        "akari_events_parsed_total", "Events parsed, by category", &["category"]
+    ).unwrap();
+
+    pub static ref EVENTS_DISCARDED: IntCounter = register_int_counter!(
+        "akari_events_discarded_total", "Events the parser couldn't turn into a structured event"
+    ).unwrap();
+
+    pub static ref PARSE_DURATION: Histogram = register_histogram!(
+        "akari_parse_duration_seconds", "Time spent parsing a single event",
+        exponential_buckets(0.00001, 4.0, 8).unwrap()
+    ).unwrap();
+
+    pub static ref QUEUE_DEPTH: IntGaugeVec = register_int_gauge_vec!(
+        "akari_queue_depth", "Events waiting in the worker queues", &["queue"]
+    ).unwrap();
+
+    // "dropped" when the queue is full, "shed" for drop_categories, "spilled" when written to the spill file
+    pub static ref QUEUE_OVERFLOW: IntCounterVec = register_int_counter_vec!(
+        "akari_queue_overflow_total", "Events that didn't fit in the queue, by what was done with them", &["action"]
+    ).unwrap();
+
+    pub static ref OUTPUT_EVENTS: IntCounterVec = register_int_counter_vec!(
+        "akari_output_events_total", "Events sent to each output, by result", &["output", "result"]
+    ).unwrap();
+
+    pub static ref OUTPUT_DURATION: HistogramVec = register_histogram_vec!(
+        "akari_output_duration_seconds", "Time spent sending a single event to each output", &["output"],
+        exponential_buckets(0.0001, 4.0, 8).unwrap()
+    ).unwrap();
+
+    pub static ref RECONNECTS: IntCounter = register_int_counter!(
+        "akari_reconnects_total", "Reconnections to the NationStates SSE feed"
+    ).unwrap();
+
+    pub static ref BACKOFF_DELAY: IntGauge = register_int_gauge!(
+        "akari_backoff_delay_seconds", "Delay before the next connection attempt"
+    ).unwrap();
+
+    pub static ref MISSED_EVENTS: IntCounter = register_int_counter!(
+        "akari_missed_events_total", "Events missed between two received events (sum of connmiss events)"
+    ).unwrap();
+
+    pub static ref EVENT_LAG: Histogram = register_histogram!(
+        "akari_event_lag_seconds", "Time between an event happening on NationStates and being output",
+        vec![0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 300.0]
+    ).unwrap();
+}
+
+pub fn encode() -> String {
+    let mut buffer = Vec::new();
+
+    if let Err(err) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        error!("Failed to encode metrics: {}", err);
+    }
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
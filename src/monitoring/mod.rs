@@ -0,0 +1,57 @@
+pub mod health;
+pub mod metrics;
+
+use std::{error::Error, sync::Arc};
+use axum::{Json, Router, extract::State, http::{StatusCode, header}, response::IntoResponse, routing::get};
+use log::{error, info};
+
+use crate::config::Config;
+use health::{HealthReport, HealthThresholds};
+
+const DEFAULT_BIND: &str = "127.0.0.1:9090";
+
+pub async fn initialize(config: &Config) -> Result<(), Box<dyn Error>> {
+    let Some(monitoring_config) = &config.monitoring else {
+        return Ok(());
+    };
+
+    if !monitoring_config.enabled { return Ok(()); }
+
+    let thresholds = Arc::new(HealthThresholds::new(monitoring_config.health.as_ref()));
+
+    let router = Router::new()
+        .route("/metrics", get(serve_metrics))
+        .route("/healthz", get(serve_liveness))
+        .route("/readyz", get(serve_readiness))
+        .with_state(thresholds);
+
+    let bind = monitoring_config.bind.as_deref().unwrap_or(DEFAULT_BIND);
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+
+    info!("Monitoring server listening on {}", bind);
+
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, router).await {
+            error!("Monitoring server stopped: {}", err);
+        }
+    });
+
+    Ok(())
+}
+
+async fn serve_metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics::encode())
+}
+
+async fn serve_liveness(State(thresholds): State<Arc<HealthThresholds>>) -> impl IntoResponse {
+    health_response(health::liveness(&thresholds))
+}
+
+async fn serve_readiness(State(thresholds): State<Arc<HealthThresholds>>) -> impl IntoResponse {
+    health_response(health::readiness(&thresholds))
+}
+
+fn health_response(report: HealthReport) -> impl IntoResponse {
+    let status = if report.healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report))
+}
@@ -6,7 +6,7 @@ use log::{warn, info};
 use futures_core::Stream;
 use futures_util::StreamExt;
 
-use crate::{events::ServerEvent, net::ExponentialBackoff};
+use crate::{events::{ServerEvent, now_timestamp_millis}, monitoring::metrics, net::ExponentialBackoff, telemetry};
 
 #[derive(Debug, Clone)]
 enum ConnectError {
@@ -61,7 +61,7 @@ impl Connection {
         let client = reqwest::Client::builder().read_timeout(Duration::from_secs(30)).build()?;
 
         let mut request = client.request(
-            Method::GET, reqwest::Url::parse(&url)?
+            Method::GET, reqwest::Url::parse(url)?
         ).header(
             "User-Agent", user_agent
         ).header(
@@ -76,6 +76,7 @@ impl Connection {
 
         if response.status() != StatusCode::OK {
             warn!(
+                error_kind = "connection";
                 "Request to www.nationstates.net returned status code {}", response.status().as_u16()
             );
 
@@ -89,13 +90,14 @@ impl Connection {
 
         let headers = response.headers();
         let content_type = headers.get("Content-Type").ok_or_else(|| {
-            warn!("Request to www.nationstates.net returned no content-type");
+            warn!(error_kind = "connection"; "Request to www.nationstates.net returned no content-type");
 
             Box::new(ConnectError::WrongContentType("".into()))
         })?.to_str()?;
 
         if !content_type.contains("event-stream") {
             warn!(
+                error_kind = "connection";
                 "Request to www.nationstates.net returned incompatible content-type '{}'", content_type
             );
 
@@ -117,11 +119,13 @@ impl Connection {
             match Self::try_connect(url, user_agent, last_event_id).await {
                 Ok(conn) => {
                     backoff.reset();
+                    metrics::BACKOFF_DELAY.set(0);
                     return conn;
                 },
                 Err(err) => {
-                    warn!("Error while connecting to www.nationstates.net: {}", err);
+                    warn!(error_kind = "connection"; "Error while connecting to www.nationstates.net: {}", err);
                     info!("Attempting to reconnect in {} seconds", backoff.delay());
+                    metrics::BACKOFF_DELAY.set(backoff.delay() as i64);
                     backoff.wait().await;
                 }
             }
@@ -165,7 +169,7 @@ impl Connection {
         Ok(result)
     }
 
-    pub fn deserialize_message(message: &String) -> Option<ServerEvent> {
+    pub fn deserialize_message(message: &str) -> Option<ServerEvent> {
         for line in message.lines() {
             let (label, data) = match line.split_once(": ") {
                 Some(v) => v,
@@ -181,7 +185,7 @@ impl Connection {
                     let event: ServerEvent = match serde_json::from_str(data) {
                         Ok(v) => v,
                         Err(err) => {
-                            warn!("Server returned malformed event '{}': {}", data, err);
+                            warn!(error_kind = "parse"; "Server returned malformed event '{}': {}", data, err);
                             return None;
                         }
                     };
@@ -202,12 +206,12 @@ impl Connection {
                 let e = match err.downcast::<reqwest::Error>() {
                     Ok(req_err) => {
                         if req_err.is_timeout() {
-                            warn!("Read from NationStates timed out: {}", req_err);
+                            warn!(error_kind = "connection"; "Read from NationStates timed out: {}", req_err);
                             return Ok(MessageResult::ResponseError);
                         }
 
                         if req_err.is_decode() {
-                            warn!("Error decoding response from NationStates: {}", req_err);
+                            warn!(error_kind = "connection"; "Error decoding response from NationStates: {}", req_err);
                             return Ok(MessageResult::ResponseError);
                         }
 
@@ -222,7 +226,15 @@ impl Connection {
             }
         }?;
 
-        let messages: Vec<ServerEvent> = raw_messages.iter().filter_map(Self::deserialize_message).collect();
+        let received_at = now_timestamp_millis();
+
+        let messages: Vec<ServerEvent> = raw_messages.iter().filter_map(|m| Self::deserialize_message(m))
+            .map(|mut event| {
+                event.received_at = Some(received_at);
+                event.context = telemetry::receive_span(&event.id);
+                event
+            })
+            .collect();
 
         if messages.is_empty() {
             return Ok(MessageResult::NoMessages);
@@ -0,0 +1,153 @@
+use std::{collections::{BTreeSet, HashSet}, error::Error, fmt};
+
+use crate::{events::SYSTEM_CATEGORIES, parser::{CATEGORY_GROUPS, COMPOSITE_GROUPS, UNPARSED_CATEGORIES}};
+
+#[derive(Debug, Clone)]
+pub enum CategoryError {
+    UnknownCategory(String),
+    UnknownGroup(String),
+    NoMatches(String),
+}
+
+impl fmt::Display for CategoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CategoryError::UnknownCategory(name) => write!(f, "'{}' is not a known category", name),
+            CategoryError::UnknownGroup(name) => write!(f, "'{}' is not a known category group", name),
+            CategoryError::NoMatches(pattern) => write!(f, "'{}' does not match any known category", pattern),
+        }
+    }
+}
+
+impl Error for CategoryError {
+}
+
+// Every category Akari can emit: parsed NS happenings, unparsed happenings and system events
+pub fn known_categories() -> BTreeSet<&'static str> {
+    CATEGORY_GROUPS.iter()
+        .flat_map(|(_, members)| members.iter().copied())
+        .chain(UNPARSED_CATEGORIES.iter().copied())
+        .chain(SYSTEM_CATEGORIES.iter().copied())
+        .collect()
+}
+
+// Category groups that filters can refer to with @name, along with their categories
+pub fn category_groups() -> Vec<(&'static str, Vec<&'static str>)> {
+    let mut groups: Vec<(&'static str, Vec<&'static str>)> = CATEGORY_GROUPS.iter()
+        .map(|(name, members)| (*name, members.to_vec()))
+        .collect();
+
+    for (name, subgroups) in COMPOSITE_GROUPS {
+        let members = CATEGORY_GROUPS.iter()
+            .filter(|(group, _)| subgroups.contains(group))
+            .flat_map(|(_, members)| members.iter().copied())
+            .collect::<BTreeSet<_>>();
+
+        groups.push((name, members.into_iter().collect()));
+    }
+
+    groups.push(("unparsed", UNPARSED_CATEGORIES.to_vec()));
+    groups.push(("system", SYSTEM_CATEGORIES.to_vec()));
+
+    groups
+}
+
+// Expands a list of category names, @groups and glob patterns (rs*, e?ject) into the categories they refer to.
+// Fails on names that don't refer to any known category, so typos in filters don't silently match nothing.
+pub fn resolve_categories<I, S>(names: I) -> Result<HashSet<String>, CategoryError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let known = known_categories();
+    let mut result = HashSet::new();
+
+    for name in names {
+        let name = name.as_ref();
+
+        if let Some(group) = name.strip_prefix('@') {
+            let Some((_, members)) = category_groups().into_iter().find(|(g, _)| *g == group) else {
+                return Err(CategoryError::UnknownGroup(name.to_owned()));
+            };
+
+            result.extend(members.into_iter().map(str::to_owned));
+        } else if name.contains(['*', '?']) {
+            let matches: Vec<&str> = known.iter().copied().filter(|c| glob_match(name, c)).collect();
+
+            if matches.is_empty() {
+                return Err(CategoryError::NoMatches(name.to_owned()));
+            }
+
+            result.extend(matches.into_iter().map(str::to_owned));
+        } else if known.contains(name) {
+            result.insert(name.to_owned());
+        } else {
+            return Err(CategoryError::UnknownCategory(name.to_owned()));
+        }
+    }
+
+    Ok(result)
+}
+
+// Matches `text` against a glob pattern where * matches any sequence of characters and ? matches one character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("rs*", "rspass"));
+        assert!(glob_match("*vote", "secrvote"));
+        assert!(glob_match("?del", "ndel"));
+        assert!(!glob_match("?del", "rdelauth"));
+        assert!(glob_match("r*del*", "rdelauth"));
+        assert!(glob_match("*", "move"));
+        assert!(!glob_match("rs*", "ereq"));
+    }
+
+    #[test]
+    fn test_resolve_categories() {
+        let resolved = resolve_categories(["@embassy"]).unwrap();
+        assert!(resolved.contains("ereq") && resolved.contains("euabort"));
+        assert_eq!(resolved.len(), 11);
+
+        let resolved = resolve_categories(["@wa", "move"]).unwrap();
+        assert!(resolved.contains("wendo") && resolved.contains("rspass") && resolved.contains("move"));
+        assert!(!resolved.contains("law"));
+
+        let resolved = resolve_categories(["rs*"]).unwrap();
+        assert!(resolved.contains("rspass") && resolved.contains("rscomply"));
+        assert!(!resolved.contains("rdiscard"));
+
+        assert!(resolve_categories(["@system"]).unwrap().contains("conninit"));
+        assert!(matches!(resolve_categories(["mvoe"]), Err(CategoryError::UnknownCategory(_))));
+        assert!(matches!(resolve_categories(["@embassies"]), Err(CategoryError::UnknownGroup(_))));
+        assert!(matches!(resolve_categories(["xyz*"]), Err(CategoryError::NoMatches(_))));
+    }
+}
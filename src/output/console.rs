@@ -1,41 +1,131 @@
-use std::error::Error;
+use std::{error::Error, fmt::Write as _, io::{self, IsTerminal, Write}};
 use log::info;
 use async_trait::async_trait;
+use time::OffsetDateTime;
 
-use crate::{output::{OutputChannel, OutputChannelFilter}, config::Config, events::ParsedEvent};
+use crate::{output::{EventTransform, OutputChannel, OutputChannelFilter}, config::{ConsoleConfig, ConsoleMode}, events::ParsedEvent};
 
 pub struct ConsoleOutput {
+    name: String,
+    mode: ConsoleMode,
+    // Only used in human mode, and only if stdout is a terminal
+    color: bool,
     filter: OutputChannelFilter,
+    transform: EventTransform,
 }
 
 #[async_trait]
 impl OutputChannel for ConsoleOutput {
-    async fn initialize(config: &Config) -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>> {
-        let Some(console_config) = &config.output.console else {
-            return Ok(None);
-        };
+    type Config = ConsoleConfig;
 
+    async fn initialize(name: &str, console_config: &ConsoleConfig) -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>> {
         if !console_config.enabled { return Ok(None); }
 
-        info!("Console output initialized");
+        info!("Console output '{}' initialized", name);
 
         Ok(Some(Box::new(Self { 
+            name: name.to_owned(),
+            mode: console_config.mode.unwrap_or_default(),
+            color: io::stdout().is_terminal(),
             filter: OutputChannelFilter::new(
                 console_config.include.clone(), 
-                console_config.exclude.clone()
-            )
+                console_config.exclude.clone(),
+                console_config.filter.as_deref()
+            )?,
+            transform: EventTransform::new(console_config.transform.as_ref())?,
         })))
     }
 
     async fn output(&mut self, event: &ParsedEvent) -> Result<(), Box<dyn Error>> {
-        if let Ok(serialized) = serde_json::to_string(event) {
-            info!("Event: {}", serialized);
-        }
+        let line = match self.mode {
+            ConsoleMode::Log => {
+                if let Ok(serialized) = self.transform.serialize(event) {
+                    info!("Event: {}", serialized);
+                }
+
+                return Ok(());
+            },
+            ConsoleMode::Compact => self.transform.serialize(event)?,
+            ConsoleMode::Pretty => serde_json::to_string_pretty(&self.transform.to_value(event)?)?,
+            ConsoleMode::Human => format_human(event, self.color),
+        };
+
+        // Flushed on every event so Akari can be piped into other programs
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()?;
 
         Ok(())
     }
 
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn get_filter(&self) -> &OutputChannelFilter {
         &self.filter
     }
-}
\ No newline at end of file
+
+    fn set_filter(&mut self, filter: OutputChannelFilter) {
+        self.filter = filter;
+    }
+
+    fn get_transform(&self) -> &EventTransform {
+        &self.transform
+    }
+}
+
+// 2024-01-01 12:34:56 #123456 move actor=testlandia origin=lazarus destination=osiris
+fn format_human(event: &ParsedEvent, color: bool) -> String {
+    let paint = |code: &str, text: &str| if color { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text.to_owned() };
+
+    let time = OffsetDateTime::from_unix_timestamp(event.time as i64)
+        .map(|time| format!("{} {:02}:{:02}:{:02}", time.date(), time.hour(), time.minute(), time.second()))
+        .unwrap_or_else(|_| event.time.to_string());
+
+    let mut line = paint("2", &time);
+
+    // System events have no NS event ID
+    if event.event >= 0 {
+        let _ = write!(line, " #{}", event.event);
+    }
+
+    let _ = write!(line, " {}", paint(if event.event >= 0 { "1;36" } else { "1;33" }, &event.category));
+
+    let fields = [
+        ("actor", &event.actor), ("receptor", &event.receptor),
+        ("origin", &event.origin), ("destination", &event.destination),
+    ];
+
+    for (key, value) in fields {
+        if let Some(value) = value {
+            let _ = write!(line, " {}{}", paint("2", &format!("{}=", key)), value);
+        }
+    }
+
+    if !event.data.is_empty() {
+        let _ = write!(line, " {}{:?}", paint("2", "data="), event.data);
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_format() {
+        let mut event = ParsedEvent::new(123, 1704112496, "move");
+        event.actor = Some("testlandia".into());
+        event.origin = Some("lazarus".into());
+        event.destination = Some("osiris".into());
+
+        assert_eq!(format_human(&event, false), "2024-01-01 12:34:56 #123 move actor=testlandia origin=lazarus destination=osiris");
+
+        let mut event = ParsedEvent::new(-1, 1704112496, "connmiss");
+        event.data = vec!["5".into(), "120".into()];
+
+        assert_eq!(format_human(&event, false), r#"2024-01-01 12:34:56 connmiss data=["5", "120"]"#);
+    }
+}
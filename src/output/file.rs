@@ -1,34 +1,33 @@
 use file_rotate::{ContentLimit, FileRotate, compression::Compression, suffix::{AppendTimestamp, FileLimit}};
-use std::{process::exit, error::Error, io::Write};
-use log::{error, info};
+use std::{error::Error, io::Write};
+use log::info;
 use async_trait::async_trait;
 
-use crate::{output::{OutputChannel, OutputChannelFilter}, config::Config, events::ParsedEvent};
+use crate::{output::{EventTransform, OutputChannel, OutputChannelFilter}, config::FileConfig, events::ParsedEvent};
 
 pub struct FileOutput {
+    name: String,
     file: FileRotate<AppendTimestamp>,
     filter: OutputChannelFilter,
+    transform: EventTransform,
 }
 
 #[async_trait]
 impl OutputChannel for FileOutput {
-    async fn initialize(config: &Config) -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>> {
-        let Some(file_config) = &config.output.file else {
-            return Ok(None);
-        };
+    type Config = FileConfig;
 
+    async fn initialize(name: &str, file_config: &FileConfig) -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>> {
         if !file_config.enabled { return Ok(None); }
 
         let Some(path) = &file_config.path else {
-            error!("File output was enabled but no path was set");
-            exit(1);
+            return Err("File output was enabled but no path was set".into());
         };
 
         let file = FileRotate::new(
             path, 
             AppendTimestamp::default(
                 file_config.maxfiles.map_or(
-                    FileLimit::Unlimited, |limit| FileLimit::MaxFiles(limit)
+                    FileLimit::Unlimited, FileLimit::MaxFiles
                 )
             ), 
             ContentLimit::Lines(
@@ -38,26 +37,46 @@ impl OutputChannel for FileOutput {
             None
         );
 
-        info!("File output initialized with path '{}'", path);
+        info!("File output '{}' initialized with path '{}'", name, path);
 
         Ok(Some(Box::new(Self { 
+            name: name.to_owned(),
             file,
             filter: OutputChannelFilter::new(
                 file_config.include.clone(), 
-                file_config.exclude.clone()
-            )
+                file_config.exclude.clone(),
+                file_config.filter.as_deref()
+            )?,
+            transform: EventTransform::new(file_config.transform.as_ref())?,
         })))
     }
 
     async fn output(&mut self, event: &ParsedEvent) -> Result<(), Box<dyn Error>> {
-        if let Ok(serialized) = serde_json::to_string(event) {
+        if let Ok(serialized) = self.transform.serialize(event) {
             writeln!(&mut self.file, "{}", serialized)?;
         }
 
         Ok(())
     }
 
+    async fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn get_filter(&self) -> &OutputChannelFilter {
         &self.filter
     }
+
+    fn set_filter(&mut self, filter: OutputChannelFilter) {
+        self.filter = filter;
+    }
+
+    fn get_transform(&self) -> &EventTransform {
+        &self.transform
+    }
 }
\ No newline at end of file
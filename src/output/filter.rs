@@ -0,0 +1,524 @@
+use std::{collections::HashSet, error::Error, fmt, iter::Peekable, str::CharIndices};
+use regex::Regex;
+
+use crate::{events::ParsedEvent, output::resolve_categories};
+
+// A compiled filter expression, such as:
+// category in [move, nfound] and destination == "the_north_pacific"
+//
+// Grammar:
+// expr       := and_expr ("or" and_expr)*
+// and_expr   := unary ("and" unary)*
+// unary      := "not" unary | "(" expr ")" | comparison
+// comparison := field ("==" | "!=") value | field ["not"] "in" list | field ("=~" | "!~") value
+// field      := category | actor | receptor | origin | destination | data | data[N]
+// list       := "[" value ("," value)* "]"
+// value      := "quoted string" | 'quoted string' | bare_word
+//
+// `data` without an index matches if any of the event's data entries match.
+// Category values may be @groups or glob patterns, and are checked against the known categories.
+#[derive(Debug)]
+pub enum FilterExpr {
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    Not(Box<FilterExpr>),
+    Compare(FilterField, FilterOp),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterField {
+    Category,
+    Actor,
+    Receptor,
+    Origin,
+    Destination,
+    Data(Option<usize>),
+}
+
+#[derive(Debug)]
+pub enum FilterOp {
+    Equals(String),
+    In(HashSet<String>),
+    Matches(Regex),
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid filter expression at position {}: {}", self.position, self.message)
+    }
+}
+
+impl Error for FilterError {
+}
+
+impl FilterExpr {
+    pub fn compile(source: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, index: 0, end: source.len() };
+
+        let expr = parser.parse_or()?;
+
+        if let Some((position, token)) = parser.tokens.get(parser.index) {
+            return Err(FilterError {
+                position: *position,
+                message: format!("unexpected {}", token),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    pub fn matches(&self, event: &ParsedEvent) -> bool {
+        match self {
+            FilterExpr::And(a, b) => a.matches(event) && b.matches(event),
+            FilterExpr::Or(a, b) => a.matches(event) || b.matches(event),
+            FilterExpr::Not(a) => !a.matches(event),
+            FilterExpr::Compare(field, op) => {
+                let test = |value: &str| match op {
+                    FilterOp::Equals(expected) => value == expected,
+                    FilterOp::In(set) => set.contains(value),
+                    FilterOp::Matches(regex) => regex.is_match(value),
+                };
+
+                match field {
+                    FilterField::Category => test(&event.category),
+                    FilterField::Actor => event.actor.as_deref().is_some_and(test),
+                    FilterField::Receptor => event.receptor.as_deref().is_some_and(test),
+                    FilterField::Origin => event.origin.as_deref().is_some_and(test),
+                    FilterField::Destination => event.destination.as_deref().is_some_and(test),
+                    FilterField::Data(None) => event.data.iter().any(|value| test(value)),
+                    FilterField::Data(Some(i)) => event.data.get(*i).is_some_and(|value| test(value)),
+                }
+            }
+        }
+    }
+}
+
+// Prints the compiled expression, with category groups and patterns expanded
+impl fmt::Display for FilterExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterExpr::And(a, b) => write!(f, "({} and {})", a, b),
+            FilterExpr::Or(a, b) => write!(f, "({} or {})", a, b),
+            FilterExpr::Not(a) => write!(f, "not {}", a),
+            FilterExpr::Compare(field, op) => {
+                match field {
+                    FilterField::Category => write!(f, "category")?,
+                    FilterField::Actor => write!(f, "actor")?,
+                    FilterField::Receptor => write!(f, "receptor")?,
+                    FilterField::Origin => write!(f, "origin")?,
+                    FilterField::Destination => write!(f, "destination")?,
+                    FilterField::Data(None) => write!(f, "data")?,
+                    FilterField::Data(Some(i)) => write!(f, "data[{}]", i)?,
+                }
+
+                match op {
+                    FilterOp::Equals(value) => write!(f, " == {:?}", value),
+                    FilterOp::In(set) => {
+                        let mut values: Vec<&String> = set.iter().collect();
+                        values.sort();
+                        write!(f, " in [{}]", values.iter().map(|v| format!("{:?}", v)).collect::<Vec<_>>().join(", "))
+                    },
+                    FilterOp::Matches(regex) => write!(f, " =~ {:?}", regex.as_str()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Equals,
+    NotEquals,
+    Matches,
+    NotMatches,
+    Word(String),
+    Quoted(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::Comma => write!(f, "','"),
+            Token::Equals => write!(f, "'=='"),
+            Token::NotEquals => write!(f, "'!='"),
+            Token::Matches => write!(f, "'=~'"),
+            Token::NotMatches => write!(f, "'!~'"),
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Quoted(value) => write!(f, "\"{}\"", value),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '@' || c == '*' || c == '?'
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = source.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ',' => Token::Comma,
+            '=' | '!' => {
+                match (c, chars.next_if(|(_, next)| *next == '=' || *next == '~')) {
+                    ('=', Some((_, '='))) => Token::Equals,
+                    ('=', Some((_, '~'))) => Token::Matches,
+                    ('!', Some((_, '='))) => Token::NotEquals,
+                    ('!', Some((_, '~'))) => Token::NotMatches,
+                    _ => return Err(FilterError {
+                        position,
+                        message: "expected '==', '!=', '=~' or '!~'".into(),
+                    }),
+                }
+            },
+            '"' | '\'' => {
+                let mut value = String::new();
+                let mut closed = false;
+
+                while let Some((_, next)) = chars.next() {
+                    match next {
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                // Keep escapes other than quotes intact, so regexes like "\d+" work
+                                if escaped != c && escaped != '\\' { value.push('\\'); }
+                                value.push(escaped);
+                            }
+                        },
+                        next if next == c => {
+                            closed = true;
+                            break;
+                        },
+                        next => value.push(next),
+                    }
+                }
+
+                if !closed {
+                    return Err(FilterError { position, message: "unterminated string".into() });
+                }
+
+                Token::Quoted(value)
+            },
+            c if is_word_char(c) => {
+                let mut word = String::from(c);
+
+                while let Some((_, next)) = chars.next_if(|(_, next)| is_word_char(*next)) {
+                    word.push(next);
+                }
+
+                Token::Word(word)
+            },
+            c => return Err(FilterError { position, message: format!("unexpected character '{}'", c) }),
+        };
+
+        tokens.push((position, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.index).map_or(self.end, |(position, _)| *position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|(_, token)| token.clone());
+        self.index += 1;
+        token
+    }
+
+    fn error(&self, message: String) -> FilterError {
+        FilterError { position: self.position(), message }
+    }
+
+    fn next_is_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w == word)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), FilterError> {
+        match self.peek() {
+            Some(token) if *token == expected => {
+                self.index += 1;
+                Ok(())
+            },
+            Some(token) => Err(self.error(format!("expected {}, found {}", expected, token))),
+            None => Err(self.error(format!("expected {}, found end of expression", expected))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpr, FilterError> {
+        let mut expr = self.parse_and()?;
+
+        while self.next_is_word("or") {
+            self.index += 1;
+            expr = FilterExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr, FilterError> {
+        let mut expr = self.parse_unary()?;
+
+        while self.next_is_word("and") {
+            self.index += 1;
+            expr = FilterExpr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpr, FilterError> {
+        if self.next_is_word("not") {
+            self.index += 1;
+            return Ok(FilterExpr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.peek() == Some(&Token::LParen) {
+            self.index += 1;
+            let expr = self.parse_or()?;
+            self.expect(Token::RParen)?;
+            return Ok(expr);
+        }
+
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<FilterExpr, FilterError> {
+        let field = self.parse_field()?;
+        let position = self.position();
+
+        let (op, negated) = match self.next() {
+            Some(Token::Equals) => (FilterOp::Equals(self.parse_value()?), false),
+            Some(Token::NotEquals) => (FilterOp::Equals(self.parse_value()?), true),
+            Some(Token::Matches) => (self.parse_regex()?, false),
+            Some(Token::NotMatches) => (self.parse_regex()?, true),
+            Some(Token::Word(word)) if word == "in" => (FilterOp::In(self.parse_list()?), false),
+            Some(Token::Word(word)) if word == "not" && self.next_is_word("in") => {
+                self.index += 1;
+                (FilterOp::In(self.parse_list()?), true)
+            },
+            _ => return Err(FilterError {
+                position,
+                message: "expected '==', '!=', '=~', '!~', 'in' or 'not in'".into(),
+            }),
+        };
+
+        // Category comparisons are resolved into the set of categories they refer to
+        let op = match (&field, op) {
+            (FilterField::Category, FilterOp::Equals(value)) => FilterOp::In(Self::resolve(position, [value])?),
+            (FilterField::Category, FilterOp::In(values)) => FilterOp::In(Self::resolve(position, values)?),
+            (_, op) => op,
+        };
+
+        let comparison = FilterExpr::Compare(field, op);
+
+        Ok(if negated { FilterExpr::Not(Box::new(comparison)) } else { comparison })
+    }
+
+    fn resolve(
+        position: usize, values: impl IntoIterator<Item = String>
+    ) -> Result<HashSet<String>, FilterError> {
+        resolve_categories(values).map_err(|err| FilterError { position, message: err.to_string() })
+    }
+
+    fn parse_field(&mut self) -> Result<FilterField, FilterError> {
+        let position = self.position();
+
+        let field = match self.next() {
+            Some(Token::Word(word)) => match word.as_str() {
+                "category" => FilterField::Category,
+                "actor" => FilterField::Actor,
+                "receptor" => FilterField::Receptor,
+                "origin" => FilterField::Origin,
+                "destination" => FilterField::Destination,
+                "data" => FilterField::Data(None),
+                _ => return Err(FilterError { position, message: format!("unknown field '{}'", word) }),
+            },
+            Some(token) => return Err(FilterError { position, message: format!("expected a field, found {}", token) }),
+            None => return Err(FilterError { position, message: "expected a field, found end of expression".into() }),
+        };
+
+        if field == FilterField::Data(None) && self.peek() == Some(&Token::LBracket) {
+            self.index += 1;
+            let position = self.position();
+
+            let index = match self.next() {
+                Some(Token::Word(word)) => word.parse::<usize>().map_err(|_| FilterError {
+                    position, message: format!("invalid data index '{}'", word)
+                })?,
+                _ => return Err(FilterError { position, message: "expected a data index".into() }),
+            };
+
+            self.expect(Token::RBracket)?;
+            return Ok(FilterField::Data(Some(index)));
+        }
+
+        Ok(field)
+    }
+
+    fn parse_value(&mut self) -> Result<String, FilterError> {
+        match self.peek() {
+            Some(Token::Word(_)) | Some(Token::Quoted(_)) => match self.next() {
+                Some(Token::Word(value)) | Some(Token::Quoted(value)) => Ok(value),
+                _ => unreachable!("token was checked by peek()"),
+            },
+            Some(token) => Err(self.error(format!("expected a value, found {}", token))),
+            None => Err(self.error("expected a value, found end of expression".into())),
+        }
+    }
+
+    fn parse_regex(&mut self) -> Result<FilterOp, FilterError> {
+        let position = self.position();
+        let pattern = self.parse_value()?;
+
+        Regex::new(&pattern).map(FilterOp::Matches).map_err(|err| FilterError {
+            position, message: format!("invalid regex: {}", err)
+        })
+    }
+
+    fn parse_list(&mut self) -> Result<HashSet<String>, FilterError> {
+        self.expect(Token::LBracket)?;
+
+        let mut values = HashSet::new();
+
+        loop {
+            values.insert(self.parse_value()?);
+
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RBracket) => break,
+                _ => {
+                    self.index -= 1;
+                    return Err(self.error("expected ',' or ']'".into()));
+                }
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_event() -> ParsedEvent {
+        let mut event = ParsedEvent::new(1, 2, "move");
+        event.actor = Some("testlandia".into());
+        event.origin = Some("the_pacific".into());
+        event.destination = Some("the_north_pacific".into());
+        event
+    }
+
+    #[test]
+    fn test_compile_and_match() {
+        let event = move_event();
+
+        let filter = FilterExpr::compile(r#"category in [move, nfound] and destination == "the_north_pacific""#).unwrap();
+        assert!(filter.matches(&event));
+
+        let filter = FilterExpr::compile("category == move and not (origin == the_pacific or actor == testlandia)").unwrap();
+        assert!(!filter.matches(&event));
+
+        let filter = FilterExpr::compile("receptor != testlandia and category not in [nfound, ncte]").unwrap();
+        assert!(filter.matches(&event));
+
+        let filter = FilterExpr::compile("receptor == testlandia").unwrap();
+        assert!(!filter.matches(&event));
+    }
+
+    #[test]
+    fn test_data_and_regex() {
+        let mut event = ParsedEvent::new(1, 2, "rmbpost");
+        event.data = vec!["123".into(), "Hello world".into()];
+
+        assert!(FilterExpr::compile(r#"data[1] =~ "(?i)^hello""#).unwrap().matches(&event));
+        assert!(FilterExpr::compile(r#"data =~ '^\d+$'"#).unwrap().matches(&event));
+        assert!(!FilterExpr::compile(r#"data[0] =~ '^\d+$' and data[2] == x"#).unwrap().matches(&event));
+        assert!(FilterExpr::compile("actor !~ '.*'").unwrap().matches(&event));
+    }
+
+    #[test]
+    fn test_category_groups() {
+        let event = move_event();
+
+        assert!(FilterExpr::compile("category in [@movement, rs*]").unwrap().matches(&event));
+        assert!(!FilterExpr::compile("category == @embassy").unwrap().matches(&event));
+    }
+
+    #[test]
+    fn test_display() {
+        let filter = FilterExpr::compile("category == @eject and not data[0] =~ '^x' or actor == a").unwrap();
+        assert_eq!(
+            filter.to_string(),
+            r#"((category in ["banject", "eject"] and not data[0] =~ "^x") or actor == "a")"#
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        let event = move_event();
+
+        // and binds tighter than or
+        let filter = FilterExpr::compile("category == nfound and actor == x or origin == the_pacific").unwrap();
+        assert!(filter.matches(&event));
+
+        let filter = FilterExpr::compile("category == nfound and (actor == x or origin == the_pacific)").unwrap();
+        assert!(!filter.matches(&event));
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let err = FilterExpr::compile("nation == testlandia").unwrap_err();
+        assert_eq!(err.position, 0);
+
+        let err = FilterExpr::compile("category in [move").unwrap_err();
+        assert_eq!(err.position, 17);
+
+        let err = FilterExpr::compile("category = move").unwrap_err();
+        assert_eq!(err.position, 9);
+
+        let err = FilterExpr::compile("actor =~ '('").unwrap_err();
+        assert_eq!(err.position, 9);
+
+        let err = FilterExpr::compile("actor == a or category in [move, nfuond]").unwrap_err();
+        assert_eq!(err.position, 23);
+
+        assert!(FilterExpr::compile("category == move move").is_err());
+        assert!(FilterExpr::compile("(category == move").is_err());
+        assert!(FilterExpr::compile("actor == 'unterminated").is_err());
+    }
+}
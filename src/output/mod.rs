@@ -1,44 +1,73 @@
+mod categories;
 mod console;
 mod file;
+mod filter;
 mod postgres;
 mod rabbitmq;
+mod transform;
 
 use std::error::Error;
 use async_trait::async_trait;
-use std::collections::HashSet;
+use log::{error, info};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-use crate::{config::Config, events::ParsedEvent};
+use crate::{config::{Config, ConfigError, OutputInstances, OutputSettings, instance_key}, events::ParsedEvent, monitoring::{health, metrics}};
 
 use console::ConsoleOutput;
 use file::FileOutput;
 use postgres::PostgresOutput;
 use rabbitmq::RabbitMQOutput;
 
+pub use categories::{category_groups, known_categories, resolve_categories};
+pub use filter::FilterExpr;
+pub use transform::EventTransform;
+
+pub use postgres::{RMB_TABLE_NAME, events_table, parse_connect_options};
+
 #[async_trait]
 pub trait OutputChannel: Send {
-    async fn initialize(config: &Config) 
+    type Config: Sync + Serialize + OutputSettings
+    where
+        Self: Sized;
+
+    async fn initialize(name: &str, config: &Self::Config) 
         -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>>
     where
         Self: Sized;
 
     async fn output(&mut self, event: &ParsedEvent) -> Result<(), Box<dyn Error>>;
 
+    // Called once after the last event, to flush buffers and close connections
+    async fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn name(&self) -> &str;
+
     fn get_filter(&self) -> &OutputChannelFilter;
+
+    // Replaces the filters from the config file, through the admin API
+    fn set_filter(&mut self, filter: OutputChannelFilter);
+
+    fn get_transform(&self) -> &EventTransform;
 }
 
 pub struct OutputChannelFilter {
     include_list: Option<HashSet<String>>,
     exclude_list: Option<HashSet<String>>,
+    expression: Option<FilterExpr>,
 }
 
 impl OutputChannelFilter {
     pub fn new(
-        include: Option<Vec<String>>, exclude: Option<Vec<String>>
-    ) -> Self {
-        Self {
-            include_list: include.map(|v| HashSet::from_iter(v.into_iter())),
-            exclude_list: exclude.map(|v| HashSet::from_iter(v.into_iter())),
-        }
+        include: Option<Vec<String>>, exclude: Option<Vec<String>>, expression: Option<&str>
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            include_list: include.map(resolve_categories).transpose()?,
+            exclude_list: exclude.map(resolve_categories).transpose()?,
+            expression: expression.map(FilterExpr::compile).transpose()?,
+        })
     }
 
     pub fn should_output_event(&self, event: &ParsedEvent) -> bool {
@@ -48,31 +77,245 @@ impl OutputChannelFilter {
         if let Some(exclude_list) = &self.exclude_list
             && exclude_list.contains(&event.category) { return false; }
 
+        if let Some(expression) = &self.expression
+            && !expression.matches(event) { return false; }
+
         true
     }
 }
 
+// Filters of a running output, as listed by the admin API
+#[derive(Serialize, Clone, Default)]
+pub struct FilterSummary {
+    pub include: Option<BTreeSet<String>>,
+    pub exclude: Option<BTreeSet<String>>,
+    pub filter: Option<String>,
+}
+
+impl From<&OutputChannelFilter> for FilterSummary {
+    fn from(filter: &OutputChannelFilter) -> Self {
+        Self {
+            include: filter.include_list.as_ref().map(|list| list.iter().cloned().collect()),
+            exclude: filter.exclude_list.as_ref().map(|list| list.iter().cloned().collect()),
+            filter: filter.expression.as_ref().map(|expression| expression.to_string()),
+        }
+    }
+}
+
 pub async fn initialize_outputs(
     config: &Config
 ) -> Result<Vec<Box<dyn OutputChannel>>, Box<dyn Error>> {
     let mut channels = Vec::new();
 
-    channels.push(RabbitMQOutput::initialize(config).await?);
-    channels.push(ConsoleOutput::initialize(config).await?);
-    channels.push(FileOutput::initialize(config).await?);
-    channels.push(PostgresOutput::initialize(config).await?);
+    initialize_instances::<RabbitMQOutput>(&mut channels, "rmq", &config.output.rmq).await?;
+    initialize_instances::<ConsoleOutput>(&mut channels, "console", &config.output.console).await?;
+    initialize_instances::<FileOutput>(&mut channels, "file", &config.output.file).await?;
+    initialize_instances::<PostgresOutput>(&mut channels, "postgres", &config.output.postgres).await?;
+
+    Ok(channels)
+}
+
+// Instances are named "type" or "type.name", e.g. "file" or "file.moves"
+fn instance_name(kind: &str, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{}.{}", kind, name),
+        None => kind.to_owned(),
+    }
+}
+
+// Differences between the outputs of two configurations. Changed instances are re-created,
+// so the old instance has to be removed before its replacement in `channels` is added.
+#[derive(Default)]
+pub struct OutputChanges {
+    pub channels: Vec<Box<dyn OutputChannel>>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+// Initializes every output that was added or changed in the new configuration.
+// Nothing is applied if any of them fails to initialize, the ones initialized so far are shut down again.
+pub async fn diff_outputs(old: &Config, new: &Config) -> Result<OutputChanges, Box<dyn Error>> {
+    let mut changes = OutputChanges::default();
+
+    if let Err(err) = diff_all_instances(&mut changes, old, new).await {
+        for mut channel in changes.channels {
+            shutdown_output(&mut channel).await;
+        }
+
+        return Err(err);
+    }
+
+    Ok(changes)
+}
+
+async fn diff_all_instances(changes: &mut OutputChanges, old: &Config, new: &Config) -> Result<(), Box<dyn Error>> {
+    diff_instances::<RabbitMQOutput>(changes, "rmq", &old.output.rmq, &new.output.rmq).await?;
+    diff_instances::<ConsoleOutput>(changes, "console", &old.output.console, &new.output.console).await?;
+    diff_instances::<FileOutput>(changes, "file", &old.output.file, &new.output.file).await?;
+    diff_instances::<PostgresOutput>(changes, "postgres", &old.output.postgres, &new.output.postgres).await?;
+
+    Ok(())
+}
+
+async fn diff_instances<T: OutputChannel>(
+    changes: &mut OutputChanges,
+    kind: &str,
+    old: &Option<OutputInstances<T::Config>>,
+    new: &Option<OutputInstances<T::Config>>,
+) -> Result<(), Box<dyn Error>> {
+    let old_instances: HashMap<String, (bool, serde_json::Value)> = old.iter()
+        .flat_map(|instances| instances.iter())
+        .map(|instance| Ok((instance_name(kind, instance.name()), (instance.enabled(), serde_json::to_value(instance)?))))
+        .collect::<Result<_, serde_json::Error>>()?;
+
+    let mut new_names = HashSet::new();
+
+    for instance in new.iter().flat_map(|instances| instances.iter()) {
+        let name = instance_name(kind, instance.name());
+        check_unique_name(&mut new_names, &name)?;
+
+        let previous = old_instances.get(&name);
+        let settings = serde_json::to_value(instance)?;
 
-    Ok(channels.into_iter().flatten().collect())
+        if previous.is_some_and(|(_, previous_settings)| *previous_settings == settings) {
+            continue;
+        }
+
+        // Changes are reported by what happens to the running outputs, disabled instances aren't running
+        match (previous.is_some_and(|(enabled, _)| *enabled), instance.enabled()) {
+            (true, true) => changes.changed.push(name.clone()),
+            (true, false) => {
+                changes.removed.push(name);
+                continue;
+            },
+            (false, true) => changes.added.push(name.clone()),
+            (false, false) => continue,
+        }
+
+        if let Some(channel) = T::initialize(&name, instance).await? {
+            changes.channels.push(channel);
+        }
+    }
+
+    changes.removed.extend(old_instances.into_iter()
+        .filter(|(name, (enabled, _))| *enabled && !new_names.contains(name))
+        .map(|(name, _)| name));
+
+    Ok(())
+}
+
+// Names of all instances of a kind are checked, enabled or not
+fn check_unique_name(names: &mut HashSet<String>, name: &str) -> Result<(), String> {
+    if !names.insert(name.to_owned()) {
+        return Err(format!("output '{}' is configured more than once, give each instance a unique name", name));
+    }
+
+    Ok(())
 }
 
-pub async fn process_outputs(
-    channels: &mut Vec<Box<dyn OutputChannel>>, event: ParsedEvent
+async fn initialize_instances<T: OutputChannel>(
+    channels: &mut Vec<Box<dyn OutputChannel>>,
+    kind: &str,
+    instances: &Option<OutputInstances<T::Config>>,
 ) -> Result<(), Box<dyn Error>> {
-    for channel in channels {
-        if channel.get_filter().should_output_event(&event) {
-            channel.output(&event).await?;
+    // Disabled instances count too, or enabling one of them later would fail to reload
+    let mut names = HashSet::new();
+
+    for instance in instances.iter().flat_map(|instances| instances.iter()) {
+        let name = instance_name(kind, instance.name());
+        check_unique_name(&mut names, &name)?;
+
+        if let Some(channel) = T::initialize(&name, instance).await? {
+            channels.push(channel);
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Resolved settings of an output instance, as shown by `akari check-config`
+#[derive(Debug)]
+pub struct OutputSummary {
+    pub name: String,
+    pub enabled: bool,
+    pub include: Option<BTreeSet<String>>,
+    pub exclude: Option<BTreeSet<String>>,
+    pub filter: Option<String>,
+    pub transform: bool,
+}
+
+// Compiles the filters and transforms of every configured output without connecting to anything
+pub fn check_outputs(config: &Config) -> Result<Vec<OutputSummary>, ConfigError> {
+    let mut summaries = Vec::new();
+
+    check_instances(&mut summaries, "rmq", &config.output.rmq)?;
+    check_instances(&mut summaries, "console", &config.output.console)?;
+    check_instances(&mut summaries, "file", &config.output.file)?;
+    check_instances(&mut summaries, "postgres", &config.output.postgres)?;
+
+    Ok(summaries)
+}
+
+fn check_instances<C: OutputSettings>(
+    summaries: &mut Vec<OutputSummary>,
+    kind: &str,
+    instances: &Option<OutputInstances<C>>,
+) -> Result<(), ConfigError> {
+    let mut names = HashSet::new();
+
+    for (i, instance) in instances.iter().flat_map(|instances| instances.iter()).enumerate() {
+        let name = instance_name(kind, instance.name());
+        let key = |field| instance_key(kind, instances, i, field);
+
+        check_unique_name(&mut names, &name).map_err(|reason| ConfigError::new(key("name"), reason))?;
+
+        let resolve = |list: Option<&Vec<String>>, field| {
+            list.map(|list| resolve_categories(list).map(|set| set.into_iter().collect::<BTreeSet<_>>()))
+                .transpose()
+                .map_err(|err| ConfigError::new(key(field), err.to_string()))
+        };
+
+        let include = resolve(instance.include(), "include")?;
+        let exclude = resolve(instance.exclude(), "exclude")?;
+
+        let filter = instance.filter().map(FilterExpr::compile).transpose()
+            .map_err(|err| ConfigError::new(key("filter"), err.to_string()))?;
+
+        EventTransform::new(instance.transform())
+            .map_err(|err| ConfigError::new(key("transform"), err.to_string()))?;
+
+        summaries.push(OutputSummary {
+            name,
+            enabled: instance.enabled(),
+            include,
+            exclude,
+            filter: filter.map(|expression| expression.to_string()),
+            transform: instance.transform().is_some(),
+        });
+    }
+
+    Ok(())
+}
+
+// Sends an event to a single output. Filters are checked by the caller.
+pub async fn output_event(channel: &mut Box<dyn OutputChannel>, event: &ParsedEvent) -> Result<(), Box<dyn Error>> {
+    let transformed = channel.get_transform().apply(event);
+
+    let timer = metrics::OUTPUT_DURATION.with_label_values(&[channel.name()]).start_timer();
+    let result = channel.output(&transformed).await;
+    timer.observe_duration();
+
+    let status = if result.is_ok() { "success" } else { "failure" };
+    metrics::OUTPUT_EVENTS.with_label_values(&[channel.name(), status]).inc();
+    health::record_output(channel.name(), result.is_ok());
+
+    result
+}
+
+pub async fn shutdown_output(channel: &mut Box<dyn OutputChannel>) {
+    match channel.shutdown().await {
+        Ok(()) => info!("Output '{}' shut down", channel.name()),
+        Err(err) => error!("Error while shutting down output '{}': {}", channel.name(), err),
+    }
+}
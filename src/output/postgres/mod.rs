@@ -0,0 +1,248 @@
+use std::{borrow::Cow, error::Error, fs::read_to_string};
+use log::{warn, info};
+use async_trait::async_trait;
+use sqlx::postgres::PgConnectOptions;
+use tokio::task::JoinHandle;
+
+use crate::{output::{EventTransform, OutputChannel, OutputChannelFilter}, config::PostgresConfig, events::ParsedEvent};
+
+mod notify;
+mod partitions;
+
+use notify::Notifier;
+use partitions::{PartitionManager, PARTITIONED_TABLE_NAME};
+
+pub struct PostgresOutput {
+    name: String,
+    pool: sqlx::PgPool,
+    filter: OutputChannelFilter,
+    transform: EventTransform,
+    skip_rmb_content: bool,
+    store_rmb_posts: bool,
+    notifier: Option<Notifier>,
+    table: &'static str,
+    // Partition maintenance loop, stopped along with the output
+    partitions: Option<JoinHandle<()>>,
+}
+
+const TABLE_NAME: &str = "akari_events";
+const SYSTEM_TABLE_NAME: &str = "akari_system_events";
+pub const RMB_TABLE_NAME: &str = "akari_rmb_posts";
+
+#[async_trait]
+impl OutputChannel for PostgresOutput {
+    type Config = PostgresConfig;
+
+    async fn initialize(name: &str, postgres_config: &PostgresConfig) -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>> {
+        if !postgres_config.enabled { return Ok(None); }
+
+        // Errors are returned rather than exiting, a reload keeps the running outputs if this one fails
+        let options = parse_connect_options(postgres_config.url.as_deref())
+            .map_err(|err| format!("Error parsing database connection parameters: {}", err))?;
+
+        let pool = sqlx::PgPool::connect_with(options).await
+            .map_err(|err| format!("Error connecting to Postgres: {}", err))?;
+
+        sqlx::migrate!().run(&pool).await?;
+
+        let mut partitions = None;
+
+        if let Some(partition_config) = &postgres_config.partitioning && partition_config.enabled {
+            let manager = PartitionManager::new(pool.clone(), partition_config);
+
+            manager.maintain().await.map_err(|err| format!("Error creating Postgres partitions: {}", err))?;
+
+            partitions = Some(tokio::spawn(manager.run()));
+        }
+
+        let table = events_table(Some(postgres_config));
+
+        info!("Postgres output '{}' connected to database and saving to table '{}'", name, table);
+
+        let store_rmb_posts = postgres_config.rmb_posts.unwrap_or(false);
+        if store_rmb_posts {
+            info!("Saving RMB message bodies to table '{}'", RMB_TABLE_NAME);
+        }
+
+        Ok(Some(Box::new(Self {
+            name: name.to_owned(),
+            pool,
+            filter: OutputChannelFilter::new(
+                postgres_config.include.clone(), 
+                postgres_config.exclude.clone(),
+                postgres_config.filter.as_deref()
+            )?,
+            transform: EventTransform::new(postgres_config.transform.as_ref())?,
+            skip_rmb_content: postgres_config.skip_rmb_content.unwrap_or(false),
+            store_rmb_posts,
+            notifier: postgres_config.notify.as_ref()
+                .filter(|notify_config| notify_config.enabled)
+                .map(Notifier::new),
+            table,
+            partitions,
+        })))
+    }
+
+    async fn output(&mut self, event: &ParsedEvent) -> Result<(), Box<dyn Error>> {
+        let has_rmb_content = event.category == "rmbpost" && event.data.len() > 1;
+
+        if self.store_rmb_posts && has_rmb_content {
+            self.save_rmb_post(event).await;
+        }
+
+        let event: Cow<ParsedEvent> = if (self.skip_rmb_content || self.store_rmb_posts) && has_rmb_content {
+            let mut event = event.clone();
+            event.data.truncate(1);
+            Cow::Owned(event)
+        } else {
+            Cow::Borrowed(event)
+        };
+
+        let result = if event.event == -1 {
+            sqlx::query(
+                &format!("INSERT INTO {} (time, category, data) VALUES ($1, $2, $3)", SYSTEM_TABLE_NAME)
+            ).bind(event.time as i64)
+            .bind(&event.category)
+            .bind(&event.data)
+            .execute(&self.pool).await
+        } else {
+            sqlx::query(
+                &format!("INSERT INTO {} (event, time, actor, receptor, origin, destination, origin_inferred, category, data)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT DO NOTHING", self.table)
+            ).bind(event.event)
+            .bind(event.time as i64)
+            .bind(&event.actor)
+            .bind(&event.receptor)
+            .bind(&event.origin)
+            .bind(&event.destination)
+            .bind(event.origin_inferred)
+            .bind(&event.category)
+            .bind(&event.data)
+            .execute(&self.pool).await
+        };
+
+        match result {
+            Ok(result) => {
+                // Events that were already saved (e.g. replayed after a reconnect) don't trigger a notification
+                if let Some(notifier) = &self.notifier && result.rows_affected() > 0 {
+                    notifier.notify(&self.pool, &event, &self.transform).await;
+                }
+            },
+            Err(err) => {
+                warn!(
+                    output = self.name.as_str(), event = event.event, category = event.category.as_str(), error_kind = "output";
+                    "Failed to save event '{:?}' to Postgres database - {:?}", event, err
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(partitions) = self.partitions.take() {
+            partitions.abort();
+        }
+
+        self.pool.close().await;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_filter(&self) -> &OutputChannelFilter {
+        &self.filter
+    }
+
+    fn set_filter(&mut self, filter: OutputChannelFilter) {
+        self.filter = filter;
+    }
+
+    fn get_transform(&self) -> &EventTransform {
+        &self.transform
+    }
+}
+
+// Also covers outputs dropped without being shut down, such as those of a rejected reload
+impl Drop for PostgresOutput {
+    fn drop(&mut self) {
+        if let Some(partitions) = &self.partitions {
+            partitions.abort();
+        }
+    }
+}
+
+impl PostgresOutput {
+    // rmbpost data is [post ID, message body], see the rmbpost processor and EventParser::parse_server_event
+    async fn save_rmb_post(&self, event: &ParsedEvent) {
+        let Ok(post_id) = event.data[0].parse::<i64>() else {
+            warn!("Event {} has invalid RMB post ID '{}'", event.event, event.data[0]);
+            return;
+        };
+
+        let result = sqlx::query(
+            &format!("INSERT INTO {} (post_id, event, time, nation, region, message)
+                VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING", RMB_TABLE_NAME)
+        ).bind(post_id)
+        .bind(event.event)
+        .bind(event.time as i64)
+        .bind(event.actor.as_deref().unwrap_or_default())
+        .bind(event.origin.as_deref().unwrap_or_default())
+        .bind(&event.data[1])
+        .execute(&self.pool).await;
+
+        if result.is_err() {
+            warn!("Failed to save RMB post {} to Postgres database - {:?}", post_id, result);
+        }
+    }
+}
+
+// The table NS happenings are saved to, depending on whether partitioning is enabled
+pub fn events_table(config: Option<&PostgresConfig>) -> &'static str {
+    match config.and_then(|c| c.partitioning.as_ref()) {
+        Some(partition_config) if partition_config.enabled => PARTITIONED_TABLE_NAME,
+        _ => TABLE_NAME,
+    }
+}
+
+// A url set in the output's configuration takes priority over the environment
+pub fn parse_connect_options(url: Option<&str>) -> Result<PgConnectOptions, Box<dyn Error + Send + Sync>> {
+    if let Some(url) = url {
+        let options: PgConnectOptions = url.parse()?;
+        return Ok(options);
+    }
+
+    if let Ok(url) = std::env::var("DATABASE_URL") {
+        let options: PgConnectOptions = url.parse()?;
+        return Ok(options);
+    }
+
+    let mut options = PgConnectOptions::new();
+
+    if let Ok(host) = std::env::var("DATABASE_HOST") {
+        options = options.host(&host);
+    }
+
+    if let Ok(port) = std::env::var("DATABASE_PORT") {
+        options = options.port(port.parse()?);
+    }
+
+    if let Ok(user) = std::env::var("DATABASE_USER") {
+        options = options.username(&user);
+    }
+
+    if let Ok(name) = std::env::var("DATABASE_NAME") {
+        options = options.database(&name);
+    }
+
+    if let Ok(passfile) = std::env::var("DATABASE_PASSWORD_FILE") {
+        let password = read_to_string(passfile)?;
+        options = options.password(&password);
+    } else if let Ok(password) = std::env::var("DATABASE_PASSWORD") {
+        options = options.password(&password);
+    }
+
+    Ok(options)
+}
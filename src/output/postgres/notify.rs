@@ -0,0 +1,49 @@
+use log::warn;
+use serde_json::json;
+
+use crate::{config::NotifyConfig, events::ParsedEvent, output::EventTransform};
+
+// Postgres rejects NOTIFY payloads of 8000 bytes or longer
+const MAX_PAYLOAD_LENGTH: usize = 7999;
+const CHANNEL_PREFIX: &str = "akari_";
+
+pub struct Notifier {
+    shared_channel: Option<String>,
+    full_payload: bool,
+}
+
+impl Notifier {
+    pub fn new(config: &NotifyConfig) -> Self {
+        Self {
+            shared_channel: config.channel.clone(),
+            full_payload: config.full_payload.unwrap_or(false),
+        }
+    }
+
+    // Payloads go through the output's transform, like the events of every other output
+    pub async fn notify(&self, pool: &sqlx::PgPool, event: &ParsedEvent, transform: &EventTransform) {
+        let channel = match &self.shared_channel {
+            Some(channel) => channel.clone(),
+            None => format!("{}{}", CHANNEL_PREFIX, event.category),
+        };
+
+        let result = sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(&channel)
+            .bind(self.payload(event, transform))
+            .execute(pool).await;
+
+        if let Err(err) = result {
+            warn!("Failed to notify channel '{}' of event {} - {:?}", channel, event.event, err);
+        }
+    }
+
+    fn payload(&self, event: &ParsedEvent, transform: &EventTransform) -> String {
+        if self.full_payload
+            && let Ok(serialized) = transform.serialize(event)
+            && serialized.len() <= MAX_PAYLOAD_LENGTH {
+            return serialized;
+        }
+
+        transform.rename(json!({ "event": event.event, "category": event.category })).to_string()
+    }
+}
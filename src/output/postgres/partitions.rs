@@ -0,0 +1,167 @@
+use std::{error::Error, time::Duration};
+use log::{info, warn};
+
+use crate::{config::{PartitionConfig, PartitionInterval}, events::now_timestamp};
+
+pub const PARTITIONED_TABLE_NAME: &str = "akari_events_partitioned";
+
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+const SECONDS_PER_DAY: u64 = 86400;
+
+pub struct PartitionManager {
+    pool: sqlx::PgPool,
+    interval: PartitionInterval,
+    premake: u32,
+    retention_days: Option<u64>,
+    detach: bool,
+    category_retention: Vec<(String, u64)>,
+}
+
+impl PartitionManager {
+    pub fn new(pool: sqlx::PgPool, config: &PartitionConfig) -> Self {
+        let retention = config.retention.as_ref();
+
+        Self {
+            pool,
+            interval: config.interval.unwrap_or(PartitionInterval::Month),
+            premake: config.premake.unwrap_or(2),
+            retention_days: retention.and_then(|r| r.days),
+            detach: retention.and_then(|r| r.detach).unwrap_or(false),
+            category_retention: retention.and_then(|r| r.categories.clone())
+                .map(|categories| categories.into_iter().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    // Repeats partition maintenance every hour. The initial pass is done by the caller with maintain().
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval_at(
+            tokio::time::Instant::now() + MAINTENANCE_INTERVAL, MAINTENANCE_INTERVAL
+        );
+
+        loop {
+            ticker.tick().await;
+
+            if let Err(err) = self.maintain().await {
+                warn!("Failed to maintain Postgres partitions: {}", err);
+            }
+        }
+    }
+
+    pub async fn maintain(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let created: i32 = sqlx::query_scalar("SELECT akari_create_partitions($1, $2)")
+            .bind(interval_name(self.interval))
+            .bind(self.premake as i32)
+            .fetch_one(&self.pool).await?;
+
+        if created > 0 {
+            info!("Created {} new partitions for table '{}'", created, PARTITIONED_TABLE_NAME);
+        }
+
+        let now = now_timestamp();
+
+        if let Some(days) = self.retention_days {
+            let removed: i32 = sqlx::query_scalar("SELECT akari_apply_retention($1, $2)")
+                .bind(now.saturating_sub(days * SECONDS_PER_DAY) as i64)
+                .bind(self.detach)
+                .fetch_one(&self.pool).await?;
+
+            if removed > 0 {
+                info!(
+                    "{} {} expired partitions from table '{}'",
+                    if self.detach { "Detached" } else { "Dropped" }, removed, PARTITIONED_TABLE_NAME
+                );
+            }
+        }
+
+        for (category, days) in &self.category_retention {
+            let result = sqlx::query(
+                &format!("DELETE FROM {} WHERE category = $1 AND time < $2", PARTITIONED_TABLE_NAME)
+            ).bind(category)
+            .bind(now.saturating_sub(days * SECONDS_PER_DAY) as i64)
+            .execute(&self.pool).await?;
+
+            if result.rows_affected() > 0 {
+                info!("Pruned {} expired '{}' events", result.rows_affected(), category);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn interval_name(interval: PartitionInterval) -> &'static str {
+    match interval {
+        PartitionInterval::Day => "day",
+        PartitionInterval::Week => "week",
+        PartitionInterval::Month => "month",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{PgPool, postgres::PgConnectOptions};
+
+    use super::*;
+
+    const TEST_DATABASE: &str = "akari_partitions_test";
+
+    // Runs in a scratch database on the server in DATABASE_URL:
+    // DATABASE_URL=postgres://user@localhost/postgres cargo test -- --ignored
+    #[tokio::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn test_maintain() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL should be set");
+        let server = PgPool::connect(&url).await.unwrap();
+
+        sqlx::query(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", TEST_DATABASE)).execute(&server).await.unwrap();
+        sqlx::query(&format!("CREATE DATABASE {}", TEST_DATABASE)).execute(&server).await.unwrap();
+
+        let options: PgConnectOptions = url.parse().unwrap();
+        let pool = PgPool::connect_with(options.database(TEST_DATABASE)).await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        let mut manager = PartitionManager {
+            pool: pool.clone(),
+            interval: PartitionInterval::Day,
+            premake: 1,
+            retention_days: None,
+            detach: false,
+            category_retention: Vec::new(),
+        };
+
+        let partitions = || sqlx::query_scalar::<_, i64>("SELECT count(*) FROM akari_partitions").fetch_one(&pool);
+
+        // Today and tomorrow, and nothing more when run again
+        manager.maintain().await.unwrap();
+        manager.maintain().await.unwrap();
+        assert_eq!(partitions().await.unwrap(), 2);
+
+        // A partition for 2000-01-01, which has expired
+        sqlx::query("CREATE TABLE akari_events_p20000101 PARTITION OF akari_events_partitioned FOR VALUES FROM (946684800) TO (946771200)")
+            .execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO akari_partitions (name, range_start, range_end) VALUES ('akari_events_p20000101', 946684800, 946771200)")
+            .execute(&pool).await.unwrap();
+
+        let recent = (now_timestamp() - 2 * SECONDS_PER_DAY) as i64;
+
+        for (event, time, category) in [(1, 946684900, "move"), (2, 1000, "move"), (3, recent, "rmbpost"), (4, recent, "move")] {
+            sqlx::query("INSERT INTO akari_events_partitioned (event, time, category) VALUES ($1, $2, $3)")
+                .bind(event as i64).bind(time).bind(category)
+                .execute(&pool).await.unwrap();
+        }
+
+        manager.retention_days = Some(30);
+        manager.category_retention = vec![("rmbpost".to_owned(), 1)];
+        manager.maintain().await.unwrap();
+
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT event FROM akari_events_partitioned ORDER BY event")
+            .fetch_all(&pool).await.unwrap();
+
+        assert_eq!(remaining, vec![4]);
+        assert_eq!(partitions().await.unwrap(), 2);
+
+        pool.close().await;
+        sqlx::query(&format!("DROP DATABASE {} WITH (FORCE)", TEST_DATABASE)).execute(&server).await.unwrap();
+    }
+}
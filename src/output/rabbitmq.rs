@@ -1,45 +1,34 @@
-use std::{process::exit, error::Error, fs::read_to_string};
-use log::{error, warn, info};
+use std::{error::Error, fs::read_to_string};
+use log::{warn, info};
 use async_trait::async_trait;
 use lapin::{
-    BasicProperties, ExchangeKind, options::{BasicPublishOptions, ConfirmSelectOptions, ExchangeDeclareOptions}, types::FieldTable, uri::AMQPUri
+    BasicProperties, ExchangeKind, options::{BasicPublishOptions, ConfirmSelectOptions, ExchangeDeclareOptions}, types::{AMQPValue, FieldTable}, uri::AMQPUri
 };
 
-use crate::{output::{OutputChannel, OutputChannelFilter}, config::Config, events::ParsedEvent};
+use crate::{output::{EventTransform, OutputChannel, OutputChannelFilter}, config::RabbitMQConfig, events::ParsedEvent, telemetry};
 
 pub struct RabbitMQOutput {
+    name: String,
     channel: lapin::Channel,
     filter: OutputChannelFilter,
+    transform: EventTransform,
 }
 
-const EXCHANGE_NAME: &'static str = "akari_events";
+const EXCHANGE_NAME: &str = "akari_events";
 
 #[async_trait]
 impl OutputChannel for RabbitMQOutput {
-    async fn initialize(config: &Config) -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>> {
-        let Some(rmq_config) = &config.output.rmq else {
-            return Ok(None);
-        };
+    type Config = RabbitMQConfig;
 
+    async fn initialize(name: &str, rmq_config: &RabbitMQConfig) -> Result<Option<Box<dyn OutputChannel>>, Box<dyn Error>> {
         if !rmq_config.enabled { return Ok(None); }
 
-        let conn = match parse_connection_uri() {
-            Ok(uri) => {
-                match lapin::Connection::connect_uri(
-                    uri, lapin::ConnectionProperties::default(),
-                ).await {
-                    Ok(conn) => conn,
-                    Err(err) => {
-                        error!("Error connecting to RabbitMQ: {}", err);
-                        exit(1);
-                    }
-                }
-            },
-            Err(err) => {
-                error!("Error parsing RabbitMQ connection parameters: {}", err);
-                exit(1);
-            }
-        };
+        // Errors are returned rather than exiting, a reload keeps the running outputs if this one fails
+        let uri = parse_connection_uri(rmq_config.url.as_deref())
+            .map_err(|err| format!("Error parsing RabbitMQ connection parameters: {}", err))?;
+
+        let conn = lapin::Connection::connect_uri(uri, lapin::ConnectionProperties::default()).await
+            .map_err(|err| format!("Error connecting to RabbitMQ: {}", err))?;
 
         let channel = conn.create_channel().await?;
 
@@ -52,66 +41,106 @@ impl OutputChannel for RabbitMQOutput {
 
         channel.confirm_select(ConfirmSelectOptions::default()).await?;
 
-        info!("Created RabbitMQ output exchange named '{}'", EXCHANGE_NAME);
+        info!("RabbitMQ output '{}' created exchange named '{}'", name, EXCHANGE_NAME);
 
         Ok(Some(Box::new(Self { 
+            name: name.to_owned(),
             channel,
             filter: OutputChannelFilter::new(
                 rmq_config.include.clone(), 
-                rmq_config.exclude.clone()
-            )
+                rmq_config.exclude.clone(),
+                rmq_config.filter.as_deref()
+            )?,
+            transform: EventTransform::new(rmq_config.transform.as_ref())?,
         })))
     }
 
     async fn output(&mut self, event: &ParsedEvent) -> Result<(), Box<dyn Error>> {
-        if let Ok(payload) = serde_json::to_string(event) {
+        if let Ok(payload) = self.transform.serialize(event) {
             let confirm = self.channel.basic_publish(
                 EXCHANGE_NAME,
                 &event.category,
                 BasicPublishOptions::default(),
                 payload.as_bytes(),
-                BasicProperties::default(),
+                BasicProperties::default().with_headers(trace_headers()),
             ).await?.await?;
 
             if !confirm.is_ack() {
-                warn!("Failed to send event '{:?}' to RabbitMQ - {:?}", event, confirm);
+                warn!(
+                    output = self.name.as_str(), event = event.event, category = event.category.as_str(), error_kind = "output";
+                    "Failed to send event '{:?}' to RabbitMQ - {:?}", event, confirm
+                );
             }
         }
 
         Ok(())
     }
 
+    async fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        self.channel.close(200, "Akari shutting down").await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn get_filter(&self) -> &OutputChannelFilter {
         &self.filter
     }
+
+    fn set_filter(&mut self, filter: OutputChannelFilter) {
+        self.filter = filter;
+    }
+
+    fn get_transform(&self) -> &EventTransform {
+        &self.transform
+    }
 }
 
-fn parse_connection_uri() -> Result<AMQPUri, Box<dyn Error + Send + Sync>> {
-    if let Some(url) = std::env::var("RABBITMQ_URL").ok() {
+// A url set in the output's configuration takes priority over the environment
+fn parse_connection_uri(url: Option<&str>) -> Result<AMQPUri, Box<dyn Error + Send + Sync>> {
+    if let Some(url) = url {
+        let uri: AMQPUri = url.parse()?;
+        return Ok(uri);
+    }
+
+    if let Ok(url) = std::env::var("RABBITMQ_URL") {
         let uri: AMQPUri = url.parse()?;
         return Ok(uri);
     }
 
     let mut uri = AMQPUri::default();
 
-    if let Some(host) = std::env::var("RABBITMQ_HOST").ok() {
+    if let Ok(host) = std::env::var("RABBITMQ_HOST") {
         uri.authority.host = host;
     }
 
-    if let Some(port) = std::env::var("RABBITMQ_PORT").ok() {
+    if let Ok(port) = std::env::var("RABBITMQ_PORT") {
         uri.authority.port = port.parse()?;
     }
 
-    if let Some(user) = std::env::var("RABBITMQ_USER").ok() {
+    if let Ok(user) = std::env::var("RABBITMQ_USER") {
         uri.authority.userinfo.username = user;
     }
 
-    if let Some(passfile) = std::env::var("RABBITMQ_PASSWORD_FILE").ok() {
+    if let Ok(passfile) = std::env::var("RABBITMQ_PASSWORD_FILE") {
         let password = read_to_string(passfile)?;
         uri.authority.userinfo.password = password;
-    } else if let Some(password) = std::env::var("RABBITMQ_PASSWORD").ok() {
+    } else if let Ok(password) = std::env::var("RABBITMQ_PASSWORD") {
         uri.authority.userinfo.password = password;
     }
 
     Ok(uri)
-}
\ No newline at end of file
+}
+
+// traceparent and tracestate headers, so consumers can continue the event's trace
+fn trace_headers() -> FieldTable {
+    let mut headers = FieldTable::default();
+
+    for (key, value) in telemetry::trace_headers() {
+        headers.insert(key.into(), AMQPValue::LongString(value.into()));
+    }
+
+    headers
+}
@@ -0,0 +1,254 @@
+use std::{borrow::Cow, collections::HashMap, error::Error};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{config::{DataTransformConfig, TransformConfig}, events::{ParsedEvent, now_timestamp_millis}, output::resolve_categories};
+
+// Fields that can be dropped or kept by a transform. event, time and category are always present.
+const OPTIONAL_FIELDS: &[&str] = &["actor", "receptor", "origin", "destination", "data"];
+const ALL_FIELDS: &[&str] = &[
+    "event", "time", "actor", "receptor", "origin", "destination", "origin_inferred", "category", "data",
+    "received_at", "parsed_at", "emitted_at",
+];
+
+#[derive(Default)]
+pub struct EventTransform {
+    dropped_fields: Vec<&'static str>,
+    renamed_fields: Vec<(String, String)>,
+    data: Option<DataTransform>,
+    categories: HashMap<String, DataTransform>,
+    latency: bool,
+}
+
+#[derive(Clone)]
+struct DataTransform {
+    max_entries: Option<usize>,
+    max_length: Option<usize>,
+    hash: Vec<usize>,
+}
+
+impl From<&DataTransformConfig> for DataTransform {
+    fn from(config: &DataTransformConfig) -> Self {
+        Self {
+            max_entries: config.max_entries,
+            max_length: config.max_length,
+            hash: config.hash.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl EventTransform {
+    pub fn new(config: Option<&TransformConfig>) -> Result<Self, Box<dyn Error>> {
+        let Some(config) = config else {
+            return Ok(Self::default());
+        };
+
+        if config.drop.is_some() && config.keep.is_some() {
+            return Err("transform can't set both 'drop' and 'keep'".into());
+        }
+
+        for field in config.drop.iter().chain(config.keep.iter()).flatten() {
+            if !OPTIONAL_FIELDS.contains(&field.as_str()) {
+                return Err(format!(
+                    "transform can't drop or keep field '{}', expected one of {:?}", field, OPTIONAL_FIELDS
+                ).into());
+            }
+        }
+
+        let dropped_fields = OPTIONAL_FIELDS.iter().copied().filter(|field| {
+            match (&config.drop, &config.keep) {
+                (Some(drop), _) => drop.iter().any(|f| f == field),
+                (_, Some(keep)) => !keep.iter().any(|f| f == field),
+                _ => false,
+            }
+        }).collect();
+
+        let mut renamed_fields = Vec::new();
+
+        for (from, to) in config.rename.iter().flatten() {
+            if !ALL_FIELDS.contains(&from.as_str()) {
+                return Err(format!("transform can't rename unknown field '{}'", from).into());
+            }
+
+            renamed_fields.push((from.clone(), to.clone()));
+        }
+
+        let mut categories = HashMap::new();
+
+        // Category keys can be groups or glob patterns, like in filters
+        for (name, category_config) in config.categories.iter().flatten() {
+            for category in resolve_categories([name])? {
+                categories.insert(category, DataTransform::from(category_config));
+            }
+        }
+
+        Ok(Self {
+            dropped_fields,
+            renamed_fields,
+            data: config.data.as_ref().map(DataTransform::from),
+            categories,
+            latency: config.latency.unwrap_or(false),
+        })
+    }
+
+    // Applies field and data transforms, right before the event is output.
+    // Events are only cloned if something actually changes.
+    pub fn apply<'a>(&self, event: &'a ParsedEvent) -> Cow<'a, ParsedEvent> {
+        let data_transform = self.categories.get(&event.category).or(self.data.as_ref());
+        let has_latency = event.received_at.is_some() || event.parsed_at.is_some();
+
+        if self.dropped_fields.is_empty() && data_transform.is_none() && !self.latency && !has_latency {
+            return Cow::Borrowed(event);
+        }
+
+        let mut event = event.clone();
+
+        if self.latency {
+            event.emitted_at = Some(now_timestamp_millis());
+        } else {
+            event.received_at = None;
+            event.parsed_at = None;
+        }
+
+        for field in &self.dropped_fields {
+            match *field {
+                "actor" => event.actor = None,
+                "receptor" => event.receptor = None,
+                "origin" => event.origin = None,
+                "destination" => event.destination = None,
+                "data" => event.data.clear(),
+                _ => unreachable!("dropped fields are validated in EventTransform::new"),
+            }
+        }
+
+        if let Some(transform) = data_transform {
+            transform.apply(&mut event.data);
+        }
+
+        Cow::Owned(event)
+    }
+
+    // Serializes an event to JSON, applying key renames
+    pub fn serialize(&self, event: &ParsedEvent) -> serde_json::Result<String> {
+        if self.renamed_fields.is_empty() {
+            return serde_json::to_string(event);
+        }
+
+        serde_json::to_string(&self.to_value(event)?)
+    }
+
+    // Same as serialize, for outputs that format the JSON themselves
+    pub fn to_value(&self, event: &ParsedEvent) -> serde_json::Result<Value> {
+        Ok(self.rename(serde_json::to_value(event)?))
+    }
+
+    // Renames the keys of an object built from an event, leaving them where they are
+    pub fn rename(&self, value: Value) -> Value {
+        let Value::Object(object) = value else {
+            return value;
+        };
+
+        Value::Object(object.into_iter().map(|(key, value)| {
+            match self.renamed_fields.iter().find(|(from, _)| *from == key) {
+                Some((_, to)) => (to.clone(), value),
+                None => (key, value),
+            }
+        }).collect())
+    }
+}
+
+impl DataTransform {
+    fn apply(&self, data: &mut Vec<String>) {
+        if let Some(max_entries) = self.max_entries {
+            data.truncate(max_entries);
+        }
+
+        for (i, value) in data.iter_mut().enumerate() {
+            if self.hash.contains(&i) {
+                *value = format!("{:x}", Sha256::digest(value.as_bytes()));
+            } else if let Some(max_length) = self.max_length
+                && let Some((index, _)) = value.char_indices().nth(max_length) {
+                value.truncate(index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rmb_event() -> ParsedEvent {
+        let mut event = ParsedEvent::new(1, 2, "rmbpost");
+        event.actor = Some("testlandia".into());
+        event.origin = Some("lazarus".into());
+        event.data = vec!["123".into(), "Hello world".into()];
+        event
+    }
+
+    #[test]
+    fn test_apply() {
+        let config: TransformConfig = serde_json::from_value(serde_json::json!({
+            "keep": ["actor", "data"],
+            "data": { "max_length": 5 },
+            "categories": { "@rmb": { "hash": [1] } },
+        })).unwrap();
+
+        let transform = EventTransform::new(Some(&config)).unwrap();
+
+        let event = rmb_event();
+        let event = transform.apply(&event);
+        assert_eq!(event.actor.as_deref(), Some("testlandia"));
+        assert!(event.origin.is_none());
+        assert_eq!(event.data[0], "123");
+        assert_eq!(event.data[1], "64ec88ca00b268e5ba1a35678a1b5316d212f4f366b2477232534a8aeca37f3c");
+
+        let mut event = ParsedEvent::new(1, 2, "law");
+        event.data = vec!["Ünïcödé text".into()];
+        assert_eq!(transform.apply(&event).data, vec!["Ünïcö"]);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let config: TransformConfig = serde_json::from_value(serde_json::json!({
+            "drop": ["data"],
+            "rename": { "actor": "nation", "origin": "region" },
+        })).unwrap();
+
+        let transform = EventTransform::new(Some(&config)).unwrap();
+        let event = rmb_event();
+        let event = transform.apply(&event);
+
+        assert_eq!(
+            transform.serialize(&event).unwrap(),
+            r#"{"event":1,"time":2,"nation":"testlandia","region":"lazarus","category":"rmbpost"}"#
+        );
+    }
+
+    #[test]
+    fn test_latency() {
+        let mut event = rmb_event();
+        event.received_at = Some(1000);
+        event.parsed_at = Some(1001);
+
+        let default = EventTransform::new(None).unwrap();
+        assert_eq!(default.apply(&event).as_ref(), &rmb_event());
+
+        let config: TransformConfig = serde_json::from_value(serde_json::json!({ "latency": true })).unwrap();
+        let transformed = EventTransform::new(Some(&config)).unwrap().apply(&event).into_owned();
+        assert_eq!((transformed.received_at, transformed.parsed_at), (Some(1000), Some(1001)));
+        assert!(transformed.emitted_at.is_some_and(|emitted_at| emitted_at >= 1001));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config: TransformConfig = serde_json::from_value(serde_json::json!({ "drop": ["category"] })).unwrap();
+        assert!(EventTransform::new(Some(&config)).is_err());
+
+        let config: TransformConfig = serde_json::from_value(serde_json::json!({ "drop": [], "keep": [] })).unwrap();
+        assert!(EventTransform::new(Some(&config)).is_err());
+
+        let config: TransformConfig = serde_json::from_value(serde_json::json!({ "rename": { "nation": "x" } })).unwrap();
+        assert!(EventTransform::new(Some(&config)).is_err());
+    }
+}
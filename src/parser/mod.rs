@@ -10,6 +10,11 @@ use crate::{events::{ParsedEvent, ServerEvent}, unicode::translate_to_unicode};
 use patterns::generate_patterns;
 use processors::{Processor, generate_processor_map};
 
+pub use patterns::{CATEGORY_GROUPS, COMPOSITE_GROUPS};
+
+// Categories given to happenings that weren't parsed, see create_generic_event()
+pub const UNPARSED_CATEGORIES: &[&str] = &["unknown", "skipped"];
+
 pub struct EventParser {
     pub patterns: Vec<(&'static str, Regex)>,
     pub regex_set: RegexSet,
@@ -58,7 +63,7 @@ impl EventParser {
 
                 parsed_event
             }).or_else(|| {
-                return Some(self.create_generic_event(&event, line, "skipped"));
+                Some(self.create_generic_event(&event, line, "skipped"))
             });
         }
 
@@ -75,7 +80,7 @@ impl EventParser {
         let matches = self.regex_set.matches(line);
 
         if !matches.matched_any() {
-            warn!("Unmatched happening line: {}", line);
+            warn!(error_kind = "parse"; "Unmatched happening line: {}", line);
             return None;
         }
 
@@ -104,6 +109,7 @@ impl EventParser {
         let Some(processor) = self.processors.get(event.category.as_str()) else {
             if !event.category.contains("skip") {
                 warn!(
+                    event = event.event, category = event.category.as_str(), error_kind = "parse";
                     "Happening {} matched category '{}' which doesn't have an associated processor", 
                     event.event, event.category
                 );
@@ -121,6 +127,7 @@ impl EventParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use opentelemetry::Context;
 
     #[test]
     fn test_extract_region_buckets() {
@@ -156,6 +163,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_category_groups() {
+        let parser = EventParser::new().unwrap();
+
+        for (category, _) in &parser.patterns {
+            if *category == "skipped" { continue; }
+
+            assert_eq!(
+                CATEGORY_GROUPS.iter().filter(|(_, members)| members.contains(category)).count(), 1,
+                "category '{}' should be part of exactly one group", category
+            );
+        }
+
+        for (group, members) in CATEGORY_GROUPS.iter() {
+            for member in members {
+                assert!(
+                    parser.patterns.iter().any(|(category, _)| category == member),
+                    "group '{}' contains unknown category '{}'", group, member
+                );
+            }
+        }
+
+        for (group, members) in COMPOSITE_GROUPS {
+            for member in *members {
+                assert!(
+                    CATEGORY_GROUPS.iter().any(|(name, _)| name == member),
+                    "composite group '{}' contains unknown group '{}'", group, member
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_simple_parse_server_event() {
         let parser = EventParser::new().unwrap();
@@ -166,6 +205,8 @@ mod tests {
             str: "@@a@@ changed a custom banner.".to_string(),
             buckets: vec!["region:b".to_string()],
             rmb_message: None,
+            received_at: None,
+            context: Context::new(),
         });
 
         assert!(event.is_some());
@@ -190,7 +231,9 @@ mod tests {
             time: "200".to_string(),
             str: r#"@@a@@ granted <i class="b"></i>Bb and <i class="c"></i>Cc authority and removed <i class="e"></i>Ex authority from @@d@@ and renamed the office from "l" to "s" in %%m%%."#.to_string(),
             buckets: vec!["region:b".to_string()],
-            rmb_message: None
+            rmb_message: None,
+            received_at: None,
+            context: Context::new(),
         });
 
         assert!(event.is_some());
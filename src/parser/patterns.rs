@@ -1,174 +1,227 @@
+use lazy_static::lazy_static;
 use regex::{Regex, RegexSet, Error};
 
-pub fn generate_patterns() -> Result<(Vec<(&'static str, Regex)>, RegexSet), Error> {
-    let patterns: Vec<(&'static str, Regex)> = vec![
-        // bucket: law
-        ("law", Regex::new(r#"^Following new legislation in @@([0-9a-z_-]+)@@, (.+)$"#)?),
-        // bucket: change
-        ("chclass", Regex::new(r#"^@@([0-9a-z_-]+)@@ was reclassified from "([A-Za-z -]+)" to "([A-Za-z -]+)"$"#)?),
-        ("chcensus", Regex::new(r#"^@@([0-9a-z_-]+)@@ was ranked in((?:,? (?:and )?the Top (?:1|5|10)% (?:of the world )?for(?:(?:,? (?:and )?(?:(?:[A-Z][A-Za-z-]+ ?)+))*))+)$"#)?),
-        ("chfield", Regex::new(r#"^@@([0-9a-z_-]+)@@ changed its national ([a-z ]+) to "([^"]*)"((?:,? (?:and )?its [a-z ]+ to "[^"]*")+)?$"#)?),
-        ("chflag", Regex::new(r#"^@@([0-9a-z_-]+)@@ altered its national flag$"#)?),
-        ("nbanner", Regex::new(r#"^@@([0-9a-z_-]+)@@ created a custom banner$"#)?),
-        ("chbanner", Regex::new(r#"^@@([0-9a-z_-]+)@@ changed a custom banner$"#)?),
-        ("chinf", Regex::new(r#"^@@([0-9a-z_-]+)@@'s influence in %%([0-9a-z_-]+)%% (rose|fell) from "([A-Za-z -]+)" to "([A-Za-z -]+)"$"#)?),
-        ("rvfield", Regex::new(r#"^@@([0-9a-z_-]+)@@ revoked its national (faith|leader|capital)$"#)?),
-        // bucket: dispatch
-        ("dispatch", Regex::new(r#"^@@([0-9a-z_-]+)@@ published "<a href="page=dispatch/id=([0-9]+)">([^><]+)</a>" \(([A-Za-z ]+): ([A-Za-z ]+)\)$"#)?),
-        // bucket: rmb
-        ("rmbpost", Regex::new(r#"^@@([0-9a-z_-]+)@@ lodged <a href="/region=(?:[0-9a-z_-]+)/page=display_region_rmb\?postid=(?:[0-9]+)#p([0-9]+)">a message</a> on the %%([0-9a-z_-]+)%% Regional Message Board$"#)?),
-        ("rmbnsupp", Regex::new(r#"^@@([0-9a-z_-]+)@@ suppressed a post on the %%([0-9a-z_-]+)%% Regional Message Board$"#)?),
-        ("rmbrsupp", Regex::new(r#"^@@([0-9a-z_-]+)@@ unsuppressed a post on the %%([0-9a-z_-]+)%% Regional Message Board$"#)?),
-        // bucket: embassy
-        ("ereq", Regex::new(r#"^@@([0-9a-z_-]+)@@ proposed constructing embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("eaccept", Regex::new(r#"^@@([0-9a-z_-]+)@@ agreed to construct embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("ecancel", Regex::new(r#"^@@([0-9a-z_-]+)@@ cancelled the closure of embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("ewish", Regex::new(r#"^@@([0-9a-z_-]+)@@ indicated that %%([0-9a-z_-]+)%% did not wish to close its embassy with %%([0-9a-z_-]+)%%$"#)?),
-        ("ereject", Regex::new(r#"^@@([0-9a-z_-]+)@@ rejected a request from %%([0-9a-z_-]+)%% for an embassy with %%([0-9a-z_-]+)%%$"#)?),
-        ("eclose", Regex::new(r#"^@@([0-9a-z_-]+)@@ ordered the closure of embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("epull", Regex::new(r#"^@@([0-9a-z_-]+)@@ withdrew a request for embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("eabort", Regex::new(r#"^@@([0-9a-z_-]+)@@ aborted construction of embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("eufinish", Regex::new(r#"^Embassy established between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("euclose", Regex::new(r#"^Embassy cancelled between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        ("euabort", Regex::new(r#"^Construction of embassies aborted between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#)?),
-        // bucket: eject
-        ("eject", Regex::new(r#"^@@([0-9a-z_-]+)@@ was ejected from %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#)?),
-        ("banject", Regex::new(r#"^@@([0-9a-z_-]+)@@ was ejected and banned from %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#)?),
-        // bucket: admin
-        ("ban", Regex::new(r#"^@@([0-9a-z_-]+)@@ banned @@([0-9a-z_-]+)@@ from %%([0-9a-z_-]+)%%$"#)?),
-        ("rcvban", Regex::new(r#"^@@([0-9a-z_-]+)@@ was banned from %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#)?),
-        ("unban", Regex::new(r#"^@@([0-9a-z_-]+)@@ removed @@([0-9a-z_-]+)@@ from the regional ban list in %%([0-9a-z_-]+)%%$"#)?),
-        ("rcvunban", Regex::new(r#"^@@([0-9a-z_-]+)@@ was removed from the regional ban list of %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#)?),
-        ("setpw", Regex::new(r#"^@@([0-9a-z_-]+)@@ password-protected %%([0-9a-z_-]+)%%$"#)?),
-        ("changepw", Regex::new(r#"^@@([0-9a-z_-]+)@@ changed the regional password in %%([0-9a-z_-]+)%%$"#)?),
-        ("rmpw", Regex::new(r#"^@@([0-9a-z_-]+)@@ removed regional password protection from %%([0-9a-z_-]+)%%$"#)?),
-        ("rupdate", Regex::new(r#"^%%([0-9a-z_-]+)%% updated$"#)?),
-        ("rfeature", Regex::new(r#"^%%([0-9a-z_-]+)%% became the Featured Region of the day$"#)?),
-        ("rmapfeat", Regex::new(r#"^%%([0-9a-z_-]+)%% became the Featured Map of the day with &&([0-9a-z_-]+)&&$"#)?),
-        ("rfound", Regex::new(r#"^@@([0-9a-z_-]+)@@ founded the region %%([0-9a-z_-]+)%%$"#)?),
-        ("srbanner", Regex::new(r#"^@@([0-9a-z_-]+)@@ set the regional banner of %%([0-9a-z_-]+)%%$"#)?),
-        ("crbanner", Regex::new(r#"^@@([0-9a-z_-]+)@@ changed the regional banner of %%([0-9a-z_-]+)%%$"#)?),
-        ("crflag", Regex::new(r#"^@@([0-9a-z_-]+)@@ altered the regional flag of %%([0-9a-z_-]+)%%$"#)?),
-        ("rrflag", Regex::new(r#"^@@([0-9a-z_-]+)@@ abolished the regional flag of %%([0-9a-z_-]+)%%$"#)?),
-        ("rmpoll", Regex::new(r#"^@@([0-9a-z_-]+)@@ deleted a regional poll in %%([0-9a-z_-]+)%%$"#)?),
-        ("rmqpoll", Regex::new(r#"^@@([0-9a-z_-]+)@@ deleted a queued regional poll in %%([0-9a-z_-]+)%%$"#)?),
-        ("addtag", Regex::new(r#"^@@([0-9a-z_-]+)@@ added the tag "([^"]+)" to %%([0-9a-z_-]+)%%$"#)?),
-        ("rmtag", Regex::new(r#"^@@([0-9a-z_-]+)@@ removed the tag "([^"]+)" from %%([0-9a-z_-]+)%%$"#)?),
-        ("roadd", Regex::new(r#"^@@([0-9a-z_-]+)@@ appointed @@([0-9a-z_-]+)@@ as (.+) with authority over (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) in %%([0-9a-z_-]+)%%$"#)?),
-        ("rorename", Regex::new(r#"^@@([0-9a-z_-]+)@@ renamed the office held by @@([0-9a-z_-]+)@@ from "(.+)" to "(.+)" in %%([0-9a-z_-]+)%%$"#)?),
-        ("rochange", Regex::new(r#"^@@([0-9a-z_-]+)@@ (granted|removed) (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority (?:and removed (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority )?(?:from|to) @@([0-9a-z_-]+)@@ as (.+) in %%([0-9a-z_-]+)%%$"#)?),
-        ("rochname", Regex::new(r#"^@@([0-9a-z_-]+)@@ (granted|removed) (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority (?:and removed (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority )?(?:from|to) @@([0-9a-z_-]+)@@ and renamed the office from "(.+)" to "(.+)" in %%([0-9a-z_-]+)%%$"#)?),
-        ("roremove", Regex::new(r#"^@@([0-9a-z_-]+)@@ dismissed @@([0-9a-z_-]+)@@ as (.+) of %%([0-9a-z_-]+)%%$"#)?),
-        ("roresign", Regex::new(r#"^@@([0-9a-z_-]+)@@ resigned as (.+) of %%([0-9a-z_-]+)%%$"#)?),
-        ("rgovtset", Regex::new(r#"^@@([0-9a-z_-]+)@@ named the Governor's office  <b>(.*)</b> in %%([0-9a-z_-]+)%%$"#)?),
-        ("rgovtupd", Regex::new(r#"^@@([0-9a-z_-]+)@@ renamed the Governor's office from "(.*)" to  <b>(.*)</b> in %%([0-9a-z_-]+)%%$"#)?),
-        ("rdelauth", Regex::new(r#"^@@([0-9a-z_-]+)@@ (granted|removed) (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+)*) authority (?:and removed (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+)*) authority )?(?:from|to) the WA Delegate (?:@@([0-9a-z_-]+)@@ )?in %%([0-9a-z_-]+)%%$"#)?),
-        ("rnewgov", Regex::new(r#"^@@([0-9a-z_-]+)@@ succeeded @@([0-9a-z_-]+)@@ as Governor of %%([0-9a-z_-]+)%%$"#)?),
-        ("rsucprio", Regex::new(r#"^@@([0-9a-z_-]+)@@ increased @@([0-9a-z_-]+)@@'s succession priority in %%([0-9a-z_-]+)%%$"#)?),
-        ("nwelcome", Regex::new(r#"^@@([0-9a-z_-]+)@@ composed a new Welcome Telegram for %%([0-9a-z_-]+)%%$"#)?),
-        ("rwelcome", Regex::new(r#"^@@([0-9a-z_-]+)@@ canceled the Welcome Telegram of %%([0-9a-z_-]+)%%$"#)?),
-        ("rwfe", Regex::new(r#"^@@([0-9a-z_-]+)@@ updated the World Factbook entry in %%([0-9a-z_-]+)%%$"#)?),
-        ("amapwf", Regex::new(r#"^@@([0-9a-z_-]+)@@ added the most supported regional map to the world factbook$"#)?),
-        ("rmapwf", Regex::new(r#"^@@([0-9a-z_-]+)@@ removed the most supported regional map from the world factbook$"#)?),
-        ("ndel", Regex::new(r#"^@@([0-9a-z_-]+)@@ became WA Delegate of %%([0-9a-z_-]+)%%$"#)?),
-        ("rdel", Regex::new(r#"^@@([0-9a-z_-]+)@@ seized the position of %%([0-9a-z_-]+)%% WA Delegate from @@([0-9a-z_-]+)@@$"#)?),
-        ("ldel", Regex::new(r#"^@@([0-9a-z_-]+)@@ lost WA Delegate status in %%([0-9a-z_-]+)%%$"#)?),
-        ("beginfn", Regex::new(r#"^@@([0-9a-z_-]+)@@ began the process of converting %%([0-9a-z_-]+)%% to a Frontier$"#)?),
-        ("stopfn", Regex::new(r#"^@@([0-9a-z_-]+)@@ canceled the process of converting %%([0-9a-z_-]+)%% to a Frontier$"#)?),
-        ("finishfn", Regex::new(r#"^%%([0-9a-z_-]+)%% became a Frontier$"#)?),
-        ("skipped", Regex::new(r#"^Became a Frontier$"#)?),
-        ("fngovrem", Regex::new(r#"^@@([0-9a-z_-]+)@@ stepped down as Governor of %%([0-9a-z_-]+)%% as it became a Frontier$"#)?),
-        ("beginst", Regex::new(r#"^@@([0-9a-z_-]+)@@ began the process of removing %%([0-9a-z_-]+)%%'s designation as a Frontier$"#)?),
-        ("stopst", Regex::new(r#"^@@([0-9a-z_-]+)@@ canceled the process of removing %%([0-9a-z_-]+)%%'s designation as a Frontier$"#)?),
-        ("finishst", Regex::new(r#"^%%([0-9a-z_-]+)%% ceased to operate as a Frontier$"#)?),
-        ("skipped", Regex::new(r#"^Ceased to operate as a Frontier$"#)?),
-        ("stgovadd", Regex::new(r#"^@@([0-9a-z_-]+)@@ became Governor of %%([0-9a-z_-]+)%%$"#)?),
-        ("annexreq", Regex::new(r#"^@@([0-9a-z_-]+)@@ sent a demand to annex %%([0-9a-z_-]+)%%$"#)?),
-        ("annexrcv", Regex::new(r#"^%%([0-9a-z_-]+)%% received a demand from @@([0-9a-z_-]+)@@ to be annexed by %%([0-9a-z_-]+)%%$"#)?),
-        ("annexrej", Regex::new(r#"^@@([0-9a-z_-]+)@@ rejected a demand for %%([0-9a-z_-]+)%% to be annexed into %%([0-9a-z_-]+)%%$"#)?),
-        ("annexacc", Regex::new(r#"^@@([0-9a-z_-]+)@@ accepted a demand to be annexed by %%([0-9a-z_-]+)%%$"#)?),
-        ("annexwth", Regex::new(r#"^@@([0-9a-z_-]+)@@ withdrew a demand to annex %%([0-9a-z_-]+)%%$"#)?),
-        ("annexfna", Regex::new(r#"^%%([0-9a-z_-]+)%% was annexed by %%([0-9a-z_-]+)%%$"#)?),
-        ("skipped", Regex::new(r#"^Annexed by %%([0-9a-z_-]+)%%$"#)?),
-        ("annexfnb", Regex::new(r#"^%%([0-9a-z_-]+)%% annexed %%([0-9a-z_-]+)%%$"#)?),
-        ("skipped", Regex::new(r#"^Annexed %%([0-9a-z_-]+)%%$"#)?),
-        ("addxrmb", Regex::new(r#"^@@([0-9a-z_-]+)@@ granted posting privileges on the %%([0-9a-z_-]+)%% Regional Message Board to ([a-zA-Z ]+) in embassy regions$"#)?),
-        ("remxrmb", Regex::new(r#"^@@([0-9a-z_-]+)@@ revoked posting privileges on the %%([0-9a-z_-]+)%% Regional Message Board from ([a-zA-Z ]+) in embassy regions$"#)?),
-        ("wzbanexp", Regex::new(r#"^Regional bans expired in %%([0-9a-z_-]+)%%$"#)?),
-        ("rgenkey", Regex::new(r#"^@@([0-9a-z_-]+)@@ generated a Telegram API Key for %%([0-9a-z_-]+)%%$"#)?),
-        // bucket: maps
-        ("mcreate", Regex::new(r#"^@@([0-9a-z_-]+)@@ created &&([0-9a-z_-]+)&&$"#)?),
-        ("mvcreate", Regex::new(r#"^@@([0-9a-z_-]+)@@ created \*\*([0-9a-z_-]+)\*\*$"#)?),
-        ("mupdate", Regex::new(r#"^@@([0-9a-z_-]+)@@ updated &&([0-9a-z_-]+)&& to \*\*([0-9a-z_-]+)\*\*$"#)?),
-        ("mendo", Regex::new(r#"^@@([0-9a-z_-]+)@@ endorsed &&([0-9a-z_-]+)&&$"#)?),
-        ("mrendo", Regex::new(r#"^@@([0-9a-z_-]+)@@ endorsed &&([0-9a-z_-]+)&& instead of &&([0-9a-z_-]+)&&$"#)?),
-        ("mlendo", Regex::new(r#"^&&([0-9a-z_-]+)&& lost the endorsement of @@([0-9a-z_-]+)@@$"#)?),
-        ("munendo", Regex::new(r#"^@@([0-9a-z_-]+)@@ removed its endorsement from &&([0-9a-z_-]+)&&$"#)?),
-        // bucket: move
-        ("move", Regex::new(r#"^@@([0-9a-z_-]+)@@ relocated from %%([0-9a-z_-]+)%% to %%([0-9a-z_-]+)%%$"#)?),
-        // bucket: found
-        ("nfound", Regex::new(r#"^@@([0-9a-z_-]+)@@ was founded in %%([0-9a-z_-]+)%%$"#)?),
-        ("nrefound", Regex::new(r#"^@@([0-9a-z_-]+)@@ was refounded in %%([0-9a-z_-]+)%%$"#)?),
-        // bucket: cte
-        ("ncte", Regex::new(r#"^@@([0-9a-z_-]+)@@ ceased to exist in %%([0-9a-z_-]+)%%$"#)?),
-        ("rgcte", Regex::new(r#"^Governor @@([0-9a-z_-]+)@@ ceased to exist$"#)?),
-        ("rfcte", Regex::new(r#"^Regional Founder @@([0-9a-z_-]+)@@ ceased to exist$"#)?),
-        // bucket: vote
-        ("wavote", Regex::new(r#"^@@([0-9a-z_-]+)@@ voted (for|against) the World Assembly Resolution "(.+)"$"#)?),
-        ("wrvote", Regex::new(r#"^@@([0-9a-z_-]+)@@ withdrew its vote on the World Assembly Resolution "(.+)"$"#)?),
-        // bucket: resolution
-        ("rsfloor", Regex::new(r#"^The (General Assembly|Security Council) proposal "(.+)" \(by @@([0-9a-z_-]+)@@((?:,?( and)? @@([0-9a-z_-]+)@@)*)?\) entered the resolution voting floor$"#)?),
-        ("rspass", Regex::new(r#"^The (General Assembly|Security Council) resolution &&(?:GA|SC)RES:(?:2|3):([0-9]+):(.+)&& was passed ([0-9,]+) votes to ([0-9,]+)(?:(?:, and recommended for adoption by all WA member nations)|(?:, and implemented in all WA member nations))?$"#)?),
-        ("rsfail", Regex::new(r#"^The (General Assembly|Security Council) resolution "(.+)" was defeated ([0-9,]+) votes to ([0-9,]+)$"#)?),
-        ("rdiscard", Regex::new(r#"^The (General Assembly|Security Council) resolution "(.+)" was discarded by the WA for rule violations after garnering ([0-9,]+) votes in favor and ([0-9,]+) votes against$"#)?),
-        ("rsapp", Regex::new(r#"^@@([0-9a-z_-]+)@@ approved the World Assembly proposal "(.+)"$"#)?),
-        ("rsremapp", Regex::new(r#"^@@([0-9a-z_-]+)@@ withdrew its approval for the World Assembly proposal "(.+)"$"#)?),
-        ("rssubmit", Regex::new(r#"^@@([0-9a-z_-]+)@@ submitted a proposal to the (General Assembly|Security Council)(?: (.+) Board)? entitled "(.+)"$"#)?),
-        ("rsremsub", Regex::new(r#"^@@([0-9a-z_-]+)@@ withdrew a proposal from the WA (General Assembly|Security Council) titled "(.+)"$"#)?),
-        ("rsquorum", Regex::new(r#"^The (General Assembly|Security Council) proposal "(.+)" \(by @@([0-9a-z_-]+)@@((?:,?( and)? @@([0-9a-z_-]+)@@)*)?\) failed to achieve quorum$"#)?),
-        ("rscensus", Regex::new(r#"^The General Assembly proposal "(.+)" \(by @@([0-9a-z_-]+)@@((?:,?( and)? @@([0-9a-z_-]+)@@)*)?\) reached quorum but could not enter the voting floor due to missing World Census analysis$"#)?),
-        ("rsmodrem", Regex::new(r#"^The proposal "(.+)" was removed from the floor$"#)?),
-        // bucket: member
-        ("wadmit", Regex::new(r#"^@@([0-9a-z_-]+)@@ was admitted to the World Assembly$"#)?),
-        ("wapply", Regex::new(r#"^@@([0-9a-z_-]+)@@ applied to join the World Assembly$"#)?),
-        ("wresign", Regex::new(r#"^@@([0-9a-z_-]+)@@ resigned from the World Assembly$"#)?),
-        ("wkick", Regex::new(r#"^@@([0-9a-z_-]+)@@ was ejected from the (?:WA for rule violations|World Assembly)$"#)?),
-        // bucket: endo
-        ("wendo", Regex::new(r#"^@@([0-9a-z_-]+)@@ endorsed @@([0-9a-z_-]+)@@$"#)?),
-        ("wunendo", Regex::new(r#"^@@([0-9a-z_-]+)@@ withdrew its endorsement from @@([0-9a-z_-]+)@@$"#)?),
-        // WA Secretariat
-        ("secenter", Regex::new(r#"^@@([0-9a-z_-]+)@@ entered the World Assembly Secretariat election$"#)?),
-        ("secvote", Regex::new(r#"^@@([0-9a-z_-]+)@@ voted for @@([0-9a-z_-]+)@@ in Round ([0-9]+) of the WASec election$"#)?),
-        ("secrvote", Regex::new(r#"^@@([0-9a-z_-]+)@@ removed its vote in Round ([0-9]+) of the WASec election$"#)?),
-        ("secelect", Regex::new(r#"^@@([0-9a-z_-]+)@@ was elected to the World Assembly Secretariat$"#)?),
-        // bucket: zombie
-        ("zhorde", Regex::new(r#"^@@([0-9a-z_-]+)@@ was ravaged by a Zombie ([A-Za-z ]+) Horde from @@([0-9a-z_-]+)@@, infecting ([0-9]+) million survivors$"#)?),
-        ("zconvert", Regex::new(r#"^@@([0-9a-z_-]+)@@ was ravaged by a Zombie ([A-Za-z ]+) Horde from @@([0-9a-z_-]+)@@, infecting ([0-9]+) million survivors and converting to a zombie exporter! Oh no!$"#)?),
-        ("zclean", Regex::new(r#"^@@([0-9a-z_-]+)@@ was cleansed by a Level ([0-9]+) ([A-Za-z ]+) Tactical Zombie Elimination Squad from @@([0-9a-z_-]+)@@, killing ([0-9]+) million zombies$"#)?),
-        ("zcleanrs", Regex::new(r#"^@@([0-9a-z_-]+)@@ was cleansed by a Level ([0-9]+) ([A-Za-z ]+) Tactical Zombie Elimination Squad from @@([0-9a-z_-]+)@@, killing ([0-9]+) million zombies and restoring to (.+)!$"#)?),
-        ("zcure", Regex::new(r#"^@@([0-9a-z_-]+)@@ was struck by a Mk ([IV]+) \(([A-Za-z ]+)\) Cure Missile from @@([0-9a-z_-]+)@@, curing ([0-9]+) million infected$"#)?),
-        ("zcurers", Regex::new(r#"^@@([0-9a-z_-]+)@@ was struck by a Mk ([IV]+) \(([A-Za-z ]+)\) Cure Missile from @@([0-9a-z_-]+)@@, curing ([0-9]+) million infected and restoring to (.+)!$"#)?),
-        // bucket: all
-        ("govabd", Regex::new(r#"^Governor @@([0-9a-z_-]+)@@ abdicated$"#)?),
-        ("npoll", Regex::new(r#"^@@([0-9a-z_-]+)@@ created a new poll in %%([0-9a-z_-]+)%%: "(.+)"$"#)?),
-        ("nqpoll", Regex::new(r#"^@@([0-9a-z_-]+)@@ queued a new poll in %%([0-9a-z_-]+)%%: "(.+)"$"#)?),
-        ("modkick", Regex::new(r#"^@@([0-9a-z_-]+)@@ was removed from %%([0-9a-z_-]+)%% by moderation$"#)?),
-        ("nrspass", Regex::new(r#"^@@([0-9a-z_-]+)@@'s resolution &&(?:GA|SC)RES:(?:2|3):([0-9]+):(.+)&& was passed by the (General Assembly|Security Council)$"#)?),
-        ("nscnom", Regex::new(r#"^@@([0-9a-z_-]+)@@ was nominated for a World Assembly (Commendation|Condemnation) by @@([0-9a-z_-]+)@@$"#)?),
-        ("rscnom", Regex::new(r#"^%%([0-9a-z_-]+)%% was nominated for a World Assembly (Commendation|Condemnation) by @@([0-9a-z_-]+)@@$"#)?),
-        ("rsctg", Regex::new(r#"^%%([0-9a-z_-]+)%% was targeted for (Liberation|Injunction) in a World Assembly proposal by @@([0-9a-z_-]+)@@$"#)?),
-        ("nscpass", Regex::new(r#"^@@([0-9a-z_-]+)@@ was (commended|condemned) by <a href="/page=WA_past_resolution/id=(?:[0-9]+)/council=2">Security Council Resolution # ([0-9]+)</a>$"#)?),
-        ("rscpass", Regex::new(r#"^%%([0-9a-z_-]+)%% was (commended|condemned|liberated|injuncted) by <a href="/page=WA_past_resolution/id=(?:[0-9]+)/council=2">Security Council Resolution # ([0-9]+)</a>$"#)?),
-        ("skipped", Regex::new(r#"^(Commended|Condemned|Liberated|Injuncted) by <a href="/page=WA_past_resolution/id=(?:[0-9]+)/council=2">Security Council Resolution # (?:[0-9]+)</a>$"#)?),
-        ("rscrep", Regex::new(r#"^(Commend|Condemn|Liberate|Injunct) resolution repealed$"#)?),
-        ("rsvtopic", Regex::new(r#"^@@([0-9a-z_-]+)@@ updated a forum topic link for WA current ([0-9]+)$"#)?),
-        ("rsptopic", Regex::new(r#"^@@([0-9a-z_-]+)@@ updated a forum topic link for WA proposal ([0-9a-z_-]+)$"#)?),
-        ("rsadopt", Regex::new(r#"^@@([0-9a-z_-]+)@@ adopted General Assembly Resolution #(?:[0-9]+) "&&GARES:3:([0-9]+):(.+)&&"$"#)?),
-        ("rscomply", Regex::new(r#"^@@([0-9a-z_-]+)@@ passed an omnibus bill to adopt all General Assembly resolutions$"#)?),
-        ("addrxrmb", Regex::new(r#"^@@([0-9a-z_-]+)@@ set embassy posting for ([a-zA-Z0-9_ ]+) to ([a-zA-Z ]+) on the %%([0-9a-z_-]+)%% Regional Message Board$"#)?),
-        ("remrxrmb", Regex::new(r#"^@@([0-9a-z_-]+)@@ blocked embassy posting from ([a-zA-Z0-9_ ]+) on the %%([0-9a-z_-]+)%% Regional Message Board$"#)?),
-        ("defrxrmb", Regex::new(r#"^@@([0-9a-z_-]+)@@ reset embassy posting for ([a-zA-Z0-9_ ]+) to global default in %%([0-9a-z_-]+)%%$"#)?),
-    ];
+pub type Patterns = Vec<(&'static str, Regex)>;
+
+// Happening patterns, grouped by NS bucket (see docs/happenings.md). Lines matching a "skipped" pattern are ignored.
+// Output filters can refer to a whole group as @name, for example @embassy.
+const PATTERN_GROUPS: &[(&str, &[(&str, &str)])] = &[
+    ("law", &[
+        ("law", r#"^Following new legislation in @@([0-9a-z_-]+)@@, (.+)$"#),
+    ]),
+    ("change", &[
+        ("chclass", r#"^@@([0-9a-z_-]+)@@ was reclassified from "([A-Za-z -]+)" to "([A-Za-z -]+)"$"#),
+        ("chcensus", r#"^@@([0-9a-z_-]+)@@ was ranked in((?:,? (?:and )?the Top (?:1|5|10)% (?:of the world )?for(?:(?:,? (?:and )?(?:(?:[A-Z][A-Za-z-]+ ?)+))*))+)$"#),
+        ("chfield", r#"^@@([0-9a-z_-]+)@@ changed its national ([a-z ]+) to "([^"]*)"((?:,? (?:and )?its [a-z ]+ to "[^"]*")+)?$"#),
+        ("chflag", r#"^@@([0-9a-z_-]+)@@ altered its national flag$"#),
+        ("nbanner", r#"^@@([0-9a-z_-]+)@@ created a custom banner$"#),
+        ("chbanner", r#"^@@([0-9a-z_-]+)@@ changed a custom banner$"#),
+        ("chinf", r#"^@@([0-9a-z_-]+)@@'s influence in %%([0-9a-z_-]+)%% (rose|fell) from "([A-Za-z -]+)" to "([A-Za-z -]+)"$"#),
+        ("rvfield", r#"^@@([0-9a-z_-]+)@@ revoked its national (faith|leader|capital)$"#),
+    ]),
+    ("dispatch", &[
+        ("dispatch", r#"^@@([0-9a-z_-]+)@@ published "<a href="page=dispatch/id=([0-9]+)">([^><]+)</a>" \(([A-Za-z ]+): ([A-Za-z ]+)\)$"#),
+    ]),
+    ("rmb", &[
+        ("rmbpost", r#"^@@([0-9a-z_-]+)@@ lodged <a href="/region=(?:[0-9a-z_-]+)/page=display_region_rmb\?postid=(?:[0-9]+)#p([0-9]+)">a message</a> on the %%([0-9a-z_-]+)%% Regional Message Board$"#),
+        ("rmbnsupp", r#"^@@([0-9a-z_-]+)@@ suppressed a post on the %%([0-9a-z_-]+)%% Regional Message Board$"#),
+        ("rmbrsupp", r#"^@@([0-9a-z_-]+)@@ unsuppressed a post on the %%([0-9a-z_-]+)%% Regional Message Board$"#),
+    ]),
+    ("embassy", &[
+        ("ereq", r#"^@@([0-9a-z_-]+)@@ proposed constructing embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("eaccept", r#"^@@([0-9a-z_-]+)@@ agreed to construct embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("ecancel", r#"^@@([0-9a-z_-]+)@@ cancelled the closure of embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("ewish", r#"^@@([0-9a-z_-]+)@@ indicated that %%([0-9a-z_-]+)%% did not wish to close its embassy with %%([0-9a-z_-]+)%%$"#),
+        ("ereject", r#"^@@([0-9a-z_-]+)@@ rejected a request from %%([0-9a-z_-]+)%% for an embassy with %%([0-9a-z_-]+)%%$"#),
+        ("eclose", r#"^@@([0-9a-z_-]+)@@ ordered the closure of embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("epull", r#"^@@([0-9a-z_-]+)@@ withdrew a request for embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("eabort", r#"^@@([0-9a-z_-]+)@@ aborted construction of embassies between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("eufinish", r#"^Embassy established between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("euclose", r#"^Embassy cancelled between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+        ("euabort", r#"^Construction of embassies aborted between %%([0-9a-z_-]+)%% and %%([0-9a-z_-]+)%%$"#),
+    ]),
+    ("eject", &[
+        ("eject", r#"^@@([0-9a-z_-]+)@@ was ejected from %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#),
+        ("banject", r#"^@@([0-9a-z_-]+)@@ was ejected and banned from %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#),
+    ]),
+    ("admin", &[
+        ("ban", r#"^@@([0-9a-z_-]+)@@ banned @@([0-9a-z_-]+)@@ from %%([0-9a-z_-]+)%%$"#),
+        ("rcvban", r#"^@@([0-9a-z_-]+)@@ was banned from %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#),
+        ("unban", r#"^@@([0-9a-z_-]+)@@ removed @@([0-9a-z_-]+)@@ from the regional ban list in %%([0-9a-z_-]+)%%$"#),
+        ("rcvunban", r#"^@@([0-9a-z_-]+)@@ was removed from the regional ban list of %%([0-9a-z_-]+)%% by @@([0-9a-z_-]+)@@$"#),
+        ("setpw", r#"^@@([0-9a-z_-]+)@@ password-protected %%([0-9a-z_-]+)%%$"#),
+        ("changepw", r#"^@@([0-9a-z_-]+)@@ changed the regional password in %%([0-9a-z_-]+)%%$"#),
+        ("rmpw", r#"^@@([0-9a-z_-]+)@@ removed regional password protection from %%([0-9a-z_-]+)%%$"#),
+        ("rupdate", r#"^%%([0-9a-z_-]+)%% updated$"#),
+        ("rfeature", r#"^%%([0-9a-z_-]+)%% became the Featured Region of the day$"#),
+        ("rmapfeat", r#"^%%([0-9a-z_-]+)%% became the Featured Map of the day with &&([0-9a-z_-]+)&&$"#),
+        ("rfound", r#"^@@([0-9a-z_-]+)@@ founded the region %%([0-9a-z_-]+)%%$"#),
+        ("srbanner", r#"^@@([0-9a-z_-]+)@@ set the regional banner of %%([0-9a-z_-]+)%%$"#),
+        ("crbanner", r#"^@@([0-9a-z_-]+)@@ changed the regional banner of %%([0-9a-z_-]+)%%$"#),
+        ("crflag", r#"^@@([0-9a-z_-]+)@@ altered the regional flag of %%([0-9a-z_-]+)%%$"#),
+        ("rrflag", r#"^@@([0-9a-z_-]+)@@ abolished the regional flag of %%([0-9a-z_-]+)%%$"#),
+        ("rmpoll", r#"^@@([0-9a-z_-]+)@@ deleted a regional poll in %%([0-9a-z_-]+)%%$"#),
+        ("rmqpoll", r#"^@@([0-9a-z_-]+)@@ deleted a queued regional poll in %%([0-9a-z_-]+)%%$"#),
+        ("addtag", r#"^@@([0-9a-z_-]+)@@ added the tag "([^"]+)" to %%([0-9a-z_-]+)%%$"#),
+        ("rmtag", r#"^@@([0-9a-z_-]+)@@ removed the tag "([^"]+)" from %%([0-9a-z_-]+)%%$"#),
+        ("roadd", r#"^@@([0-9a-z_-]+)@@ appointed @@([0-9a-z_-]+)@@ as (.+) with authority over (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) in %%([0-9a-z_-]+)%%$"#),
+        ("rorename", r#"^@@([0-9a-z_-]+)@@ renamed the office held by @@([0-9a-z_-]+)@@ from "(.+)" to "(.+)" in %%([0-9a-z_-]+)%%$"#),
+        ("rochange", r#"^@@([0-9a-z_-]+)@@ (granted|removed) (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority (?:and removed (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority )?(?:from|to) @@([0-9a-z_-]+)@@ as (.+) in %%([0-9a-z_-]+)%%$"#),
+        ("rochname", r#"^@@([0-9a-z_-]+)@@ (granted|removed) (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority (?:and removed (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+?)*) authority )?(?:from|to) @@([0-9a-z_-]+)@@ and renamed the office from "(.+)" to "(.+)" in %%([0-9a-z_-]+)%%$"#),
+        ("roremove", r#"^@@([0-9a-z_-]+)@@ dismissed @@([0-9a-z_-]+)@@ as (.+) of %%([0-9a-z_-]+)%%$"#),
+        ("roresign", r#"^@@([0-9a-z_-]+)@@ resigned as (.+) of %%([0-9a-z_-]+)%%$"#),
+        ("rgovtset", r#"^@@([0-9a-z_-]+)@@ named the Governor's office  <b>(.*)</b> in %%([0-9a-z_-]+)%%$"#),
+        ("rgovtupd", r#"^@@([0-9a-z_-]+)@@ renamed the Governor's office from "(.*)" to  <b>(.*)</b> in %%([0-9a-z_-]+)%%$"#),
+        ("rdelauth", r#"^@@([0-9a-z_-]+)@@ (granted|removed) (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+)*) authority (?:and removed (<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+(?:,? (?:and )?<i class="[a-z0-9\-]+"></i>[a-zA-Z ]+)*) authority )?(?:from|to) the WA Delegate (?:@@([0-9a-z_-]+)@@ )?in %%([0-9a-z_-]+)%%$"#),
+        ("rnewgov", r#"^@@([0-9a-z_-]+)@@ succeeded @@([0-9a-z_-]+)@@ as Governor of %%([0-9a-z_-]+)%%$"#),
+        ("rsucprio", r#"^@@([0-9a-z_-]+)@@ increased @@([0-9a-z_-]+)@@'s succession priority in %%([0-9a-z_-]+)%%$"#),
+        ("nwelcome", r#"^@@([0-9a-z_-]+)@@ composed a new Welcome Telegram for %%([0-9a-z_-]+)%%$"#),
+        ("rwelcome", r#"^@@([0-9a-z_-]+)@@ canceled the Welcome Telegram of %%([0-9a-z_-]+)%%$"#),
+        ("rwfe", r#"^@@([0-9a-z_-]+)@@ updated the World Factbook entry in %%([0-9a-z_-]+)%%$"#),
+        ("amapwf", r#"^@@([0-9a-z_-]+)@@ added the most supported regional map to the world factbook$"#),
+        ("rmapwf", r#"^@@([0-9a-z_-]+)@@ removed the most supported regional map from the world factbook$"#),
+        ("ndel", r#"^@@([0-9a-z_-]+)@@ became WA Delegate of %%([0-9a-z_-]+)%%$"#),
+        ("rdel", r#"^@@([0-9a-z_-]+)@@ seized the position of %%([0-9a-z_-]+)%% WA Delegate from @@([0-9a-z_-]+)@@$"#),
+        ("ldel", r#"^@@([0-9a-z_-]+)@@ lost WA Delegate status in %%([0-9a-z_-]+)%%$"#),
+        ("beginfn", r#"^@@([0-9a-z_-]+)@@ began the process of converting %%([0-9a-z_-]+)%% to a Frontier$"#),
+        ("stopfn", r#"^@@([0-9a-z_-]+)@@ canceled the process of converting %%([0-9a-z_-]+)%% to a Frontier$"#),
+        ("finishfn", r#"^%%([0-9a-z_-]+)%% became a Frontier$"#),
+        ("skipped", r#"^Became a Frontier$"#),
+        ("fngovrem", r#"^@@([0-9a-z_-]+)@@ stepped down as Governor of %%([0-9a-z_-]+)%% as it became a Frontier$"#),
+        ("beginst", r#"^@@([0-9a-z_-]+)@@ began the process of removing %%([0-9a-z_-]+)%%'s designation as a Frontier$"#),
+        ("stopst", r#"^@@([0-9a-z_-]+)@@ canceled the process of removing %%([0-9a-z_-]+)%%'s designation as a Frontier$"#),
+        ("finishst", r#"^%%([0-9a-z_-]+)%% ceased to operate as a Frontier$"#),
+        ("skipped", r#"^Ceased to operate as a Frontier$"#),
+        ("stgovadd", r#"^@@([0-9a-z_-]+)@@ became Governor of %%([0-9a-z_-]+)%%$"#),
+        ("annexreq", r#"^@@([0-9a-z_-]+)@@ sent a demand to annex %%([0-9a-z_-]+)%%$"#),
+        ("annexrcv", r#"^%%([0-9a-z_-]+)%% received a demand from @@([0-9a-z_-]+)@@ to be annexed by %%([0-9a-z_-]+)%%$"#),
+        ("annexrej", r#"^@@([0-9a-z_-]+)@@ rejected a demand for %%([0-9a-z_-]+)%% to be annexed into %%([0-9a-z_-]+)%%$"#),
+        ("annexacc", r#"^@@([0-9a-z_-]+)@@ accepted a demand to be annexed by %%([0-9a-z_-]+)%%$"#),
+        ("annexwth", r#"^@@([0-9a-z_-]+)@@ withdrew a demand to annex %%([0-9a-z_-]+)%%$"#),
+        ("annexfna", r#"^%%([0-9a-z_-]+)%% was annexed by %%([0-9a-z_-]+)%%$"#),
+        ("skipped", r#"^Annexed by %%([0-9a-z_-]+)%%$"#),
+        ("annexfnb", r#"^%%([0-9a-z_-]+)%% annexed %%([0-9a-z_-]+)%%$"#),
+        ("skipped", r#"^Annexed %%([0-9a-z_-]+)%%$"#),
+        ("addxrmb", r#"^@@([0-9a-z_-]+)@@ granted posting privileges on the %%([0-9a-z_-]+)%% Regional Message Board to ([a-zA-Z ]+) in embassy regions$"#),
+        ("remxrmb", r#"^@@([0-9a-z_-]+)@@ revoked posting privileges on the %%([0-9a-z_-]+)%% Regional Message Board from ([a-zA-Z ]+) in embassy regions$"#),
+        ("wzbanexp", r#"^Regional bans expired in %%([0-9a-z_-]+)%%$"#),
+        ("rgenkey", r#"^@@([0-9a-z_-]+)@@ generated a Telegram API Key for %%([0-9a-z_-]+)%%$"#),
+    ]),
+    ("maps", &[
+        ("mcreate", r#"^@@([0-9a-z_-]+)@@ created &&([0-9a-z_-]+)&&$"#),
+        ("mvcreate", r#"^@@([0-9a-z_-]+)@@ created \*\*([0-9a-z_-]+)\*\*$"#),
+        ("mupdate", r#"^@@([0-9a-z_-]+)@@ updated &&([0-9a-z_-]+)&& to \*\*([0-9a-z_-]+)\*\*$"#),
+        ("mendo", r#"^@@([0-9a-z_-]+)@@ endorsed &&([0-9a-z_-]+)&&$"#),
+        ("mrendo", r#"^@@([0-9a-z_-]+)@@ endorsed &&([0-9a-z_-]+)&& instead of &&([0-9a-z_-]+)&&$"#),
+        ("mlendo", r#"^&&([0-9a-z_-]+)&& lost the endorsement of @@([0-9a-z_-]+)@@$"#),
+        ("munendo", r#"^@@([0-9a-z_-]+)@@ removed its endorsement from &&([0-9a-z_-]+)&&$"#),
+    ]),
+    ("move", &[
+        ("move", r#"^@@([0-9a-z_-]+)@@ relocated from %%([0-9a-z_-]+)%% to %%([0-9a-z_-]+)%%$"#),
+    ]),
+    ("founding", &[
+        ("nfound", r#"^@@([0-9a-z_-]+)@@ was founded in %%([0-9a-z_-]+)%%$"#),
+        ("nrefound", r#"^@@([0-9a-z_-]+)@@ was refounded in %%([0-9a-z_-]+)%%$"#),
+    ]),
+    ("cte", &[
+        ("ncte", r#"^@@([0-9a-z_-]+)@@ ceased to exist in %%([0-9a-z_-]+)%%$"#),
+        ("rgcte", r#"^Governor @@([0-9a-z_-]+)@@ ceased to exist$"#),
+        ("rfcte", r#"^Regional Founder @@([0-9a-z_-]+)@@ ceased to exist$"#),
+    ]),
+    ("vote", &[
+        ("wavote", r#"^@@([0-9a-z_-]+)@@ voted (for|against) the World Assembly Resolution "(.+)"$"#),
+        ("wrvote", r#"^@@([0-9a-z_-]+)@@ withdrew its vote on the World Assembly Resolution "(.+)"$"#),
+    ]),
+    ("resolution", &[
+        ("rsfloor", r#"^The (General Assembly|Security Council) proposal "(.+)" \(by @@([0-9a-z_-]+)@@((?:,?( and)? @@([0-9a-z_-]+)@@)*)?\) entered the resolution voting floor$"#),
+        ("rspass", r#"^The (General Assembly|Security Council) resolution &&(?:GA|SC)RES:(?:2|3):([0-9]+):(.+)&& was passed ([0-9,]+) votes to ([0-9,]+)(?:(?:, and recommended for adoption by all WA member nations)|(?:, and implemented in all WA member nations))?$"#),
+        ("rsfail", r#"^The (General Assembly|Security Council) resolution "(.+)" was defeated ([0-9,]+) votes to ([0-9,]+)$"#),
+        ("rdiscard", r#"^The (General Assembly|Security Council) resolution "(.+)" was discarded by the WA for rule violations after garnering ([0-9,]+) votes in favor and ([0-9,]+) votes against$"#),
+        ("rsapp", r#"^@@([0-9a-z_-]+)@@ approved the World Assembly proposal "(.+)"$"#),
+        ("rsremapp", r#"^@@([0-9a-z_-]+)@@ withdrew its approval for the World Assembly proposal "(.+)"$"#),
+        ("rssubmit", r#"^@@([0-9a-z_-]+)@@ submitted a proposal to the (General Assembly|Security Council)(?: (.+) Board)? entitled "(.+)"$"#),
+        ("rsremsub", r#"^@@([0-9a-z_-]+)@@ withdrew a proposal from the WA (General Assembly|Security Council) titled "(.+)"$"#),
+        ("rsquorum", r#"^The (General Assembly|Security Council) proposal "(.+)" \(by @@([0-9a-z_-]+)@@((?:,?( and)? @@([0-9a-z_-]+)@@)*)?\) failed to achieve quorum$"#),
+        ("rscensus", r#"^The General Assembly proposal "(.+)" \(by @@([0-9a-z_-]+)@@((?:,?( and)? @@([0-9a-z_-]+)@@)*)?\) reached quorum but could not enter the voting floor due to missing World Census analysis$"#),
+        ("rsmodrem", r#"^The proposal "(.+)" was removed from the floor$"#),
+    ]),
+    ("member", &[
+        ("wadmit", r#"^@@([0-9a-z_-]+)@@ was admitted to the World Assembly$"#),
+        ("wapply", r#"^@@([0-9a-z_-]+)@@ applied to join the World Assembly$"#),
+        ("wresign", r#"^@@([0-9a-z_-]+)@@ resigned from the World Assembly$"#),
+        ("wkick", r#"^@@([0-9a-z_-]+)@@ was ejected from the (?:WA for rule violations|World Assembly)$"#),
+    ]),
+    ("endo", &[
+        ("wendo", r#"^@@([0-9a-z_-]+)@@ endorsed @@([0-9a-z_-]+)@@$"#),
+        ("wunendo", r#"^@@([0-9a-z_-]+)@@ withdrew its endorsement from @@([0-9a-z_-]+)@@$"#),
+    ]),
+    // Not an NS bucket, see the WA Secretariat section of docs/happenings.md
+    ("secretariat", &[
+        ("secenter", r#"^@@([0-9a-z_-]+)@@ entered the World Assembly Secretariat election$"#),
+        ("secvote", r#"^@@([0-9a-z_-]+)@@ voted for @@([0-9a-z_-]+)@@ in Round ([0-9]+) of the WASec election$"#),
+        ("secrvote", r#"^@@([0-9a-z_-]+)@@ removed its vote in Round ([0-9]+) of the WASec election$"#),
+        ("secelect", r#"^@@([0-9a-z_-]+)@@ was elected to the World Assembly Secretariat$"#),
+    ]),
+    ("zombie", &[
+        ("zhorde", r#"^@@([0-9a-z_-]+)@@ was ravaged by a Zombie ([A-Za-z ]+) Horde from @@([0-9a-z_-]+)@@, infecting ([0-9]+) million survivors$"#),
+        ("zconvert", r#"^@@([0-9a-z_-]+)@@ was ravaged by a Zombie ([A-Za-z ]+) Horde from @@([0-9a-z_-]+)@@, infecting ([0-9]+) million survivors and converting to a zombie exporter! Oh no!$"#),
+        ("zclean", r#"^@@([0-9a-z_-]+)@@ was cleansed by a Level ([0-9]+) ([A-Za-z ]+) Tactical Zombie Elimination Squad from @@([0-9a-z_-]+)@@, killing ([0-9]+) million zombies$"#),
+        ("zcleanrs", r#"^@@([0-9a-z_-]+)@@ was cleansed by a Level ([0-9]+) ([A-Za-z ]+) Tactical Zombie Elimination Squad from @@([0-9a-z_-]+)@@, killing ([0-9]+) million zombies and restoring to (.+)!$"#),
+        ("zcure", r#"^@@([0-9a-z_-]+)@@ was struck by a Mk ([IV]+) \(([A-Za-z ]+)\) Cure Missile from @@([0-9a-z_-]+)@@, curing ([0-9]+) million infected$"#),
+        ("zcurers", r#"^@@([0-9a-z_-]+)@@ was struck by a Mk ([IV]+) \(([A-Za-z ]+)\) Cure Missile from @@([0-9a-z_-]+)@@, curing ([0-9]+) million infected and restoring to (.+)!$"#),
+    ]),
+    // bucket: all, happenings with no bucket of their own
+    ("generic", &[
+        ("govabd", r#"^Governor @@([0-9a-z_-]+)@@ abdicated$"#),
+        ("npoll", r#"^@@([0-9a-z_-]+)@@ created a new poll in %%([0-9a-z_-]+)%%: "(.+)"$"#),
+        ("nqpoll", r#"^@@([0-9a-z_-]+)@@ queued a new poll in %%([0-9a-z_-]+)%%: "(.+)"$"#),
+        ("modkick", r#"^@@([0-9a-z_-]+)@@ was removed from %%([0-9a-z_-]+)%% by moderation$"#),
+        ("nrspass", r#"^@@([0-9a-z_-]+)@@'s resolution &&(?:GA|SC)RES:(?:2|3):([0-9]+):(.+)&& was passed by the (General Assembly|Security Council)$"#),
+        ("nscnom", r#"^@@([0-9a-z_-]+)@@ was nominated for a World Assembly (Commendation|Condemnation) by @@([0-9a-z_-]+)@@$"#),
+        ("rscnom", r#"^%%([0-9a-z_-]+)%% was nominated for a World Assembly (Commendation|Condemnation) by @@([0-9a-z_-]+)@@$"#),
+        ("rsctg", r#"^%%([0-9a-z_-]+)%% was targeted for (Liberation|Injunction) in a World Assembly proposal by @@([0-9a-z_-]+)@@$"#),
+        ("nscpass", r#"^@@([0-9a-z_-]+)@@ was (commended|condemned) by <a href="/page=WA_past_resolution/id=(?:[0-9]+)/council=2">Security Council Resolution # ([0-9]+)</a>$"#),
+        ("rscpass", r#"^%%([0-9a-z_-]+)%% was (commended|condemned|liberated|injuncted) by <a href="/page=WA_past_resolution/id=(?:[0-9]+)/council=2">Security Council Resolution # ([0-9]+)</a>$"#),
+        ("skipped", r#"^(Commended|Condemned|Liberated|Injuncted) by <a href="/page=WA_past_resolution/id=(?:[0-9]+)/council=2">Security Council Resolution # (?:[0-9]+)</a>$"#),
+        ("rscrep", r#"^(Commend|Condemn|Liberate|Injunct) resolution repealed$"#),
+        ("rsvtopic", r#"^@@([0-9a-z_-]+)@@ updated a forum topic link for WA current ([0-9]+)$"#),
+        ("rsptopic", r#"^@@([0-9a-z_-]+)@@ updated a forum topic link for WA proposal ([0-9a-z_-]+)$"#),
+        ("rsadopt", r#"^@@([0-9a-z_-]+)@@ adopted General Assembly Resolution #(?:[0-9]+) "&&GARES:3:([0-9]+):(.+)&&"$"#),
+        ("rscomply", r#"^@@([0-9a-z_-]+)@@ passed an omnibus bill to adopt all General Assembly resolutions$"#),
+        ("addrxrmb", r#"^@@([0-9a-z_-]+)@@ set embassy posting for ([a-zA-Z0-9_ ]+) to ([a-zA-Z ]+) on the %%([0-9a-z_-]+)%% Regional Message Board$"#),
+        ("remrxrmb", r#"^@@([0-9a-z_-]+)@@ blocked embassy posting from ([a-zA-Z0-9_ ]+) on the %%([0-9a-z_-]+)%% Regional Message Board$"#),
+        ("defrxrmb", r#"^@@([0-9a-z_-]+)@@ reset embassy posting for ([a-zA-Z0-9_ ]+) to global default in %%([0-9a-z_-]+)%%$"#),
+    ]),
+];
+
+lazy_static! {
+    // Categories of each group, in the order of their patterns
+    pub static ref CATEGORY_GROUPS: Vec<(&'static str, Vec<&'static str>)> = PATTERN_GROUPS.iter().map(|(group, patterns)| {
+        let mut members: Vec<&'static str> = Vec::new();
+
+        for (category, _) in *patterns {
+            if *category != "skipped" && !members.contains(category) {
+                members.push(category);
+            }
+        }
+
+        (*group, members)
+    }).collect();
+}
+
+// Groups made up of other groups, following the sections of docs/happenings.md
+pub const COMPOSITE_GROUPS: &[(&str, &[&str])] = &[
+    ("nations", &["law", "change", "dispatch"]),
+    ("regions", &["rmb", "embassy", "eject", "admin", "maps"]),
+    ("movement", &["move", "founding", "cte"]),
+    ("wa", &["vote", "resolution", "member", "endo", "secretariat"]),
+];
+
+pub fn generate_patterns() -> Result<(Patterns, RegexSet), Error> {
+    let patterns = PATTERN_GROUPS.iter()
+        .flat_map(|(_, patterns)| patterns.iter())
+        .map(|(category, pattern)| Ok((*category, Regex::new(pattern)?)))
+        .collect::<Result<Patterns, Error>>()?;
 
     let regex_set = RegexSet::new(
         patterns.iter().map(|(_, pattern)| { pattern.as_str() })
@@ -176,4 +229,3 @@ pub fn generate_patterns() -> Result<(Vec<(&'static str, Regex)>, RegexSet), Err
 
     Ok((patterns, regex_set))
 }
-
@@ -2,7 +2,7 @@ use lazy_static::lazy_static;
 use regex::{Captures, Regex};
 use std::{borrow::Cow, collections::HashMap};
 
-use crate::events::ParsedEvent;
+use crate::events::{ParsedEvent, UNKNOWN_ORIGIN};
 
 pub enum Field {
     Actor(usize),
@@ -205,7 +205,7 @@ impl Processor {
                         }
                     }
                 },
-                BucketOrigin => event.origin = Some(regions.first().unwrap_or(&"[unknown]").to_string())
+                BucketOrigin => event.origin = Some(regions.first().unwrap_or(&UNKNOWN_ORIGIN).to_string())
             }
         }
 
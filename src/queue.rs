@@ -0,0 +1,379 @@
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error, fs::{self, File, OpenOptions}, io::{BufRead, BufReader, Seek, SeekFrom, Write},
+    path::PathBuf, sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}}, time::Duration,
+};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, WeakUnboundedSender, unbounded_channel};
+
+use crate::config::{QueueConfig, QueuePolicy};
+use crate::events::{SYSTEM_CATEGORIES, SequencedEvent, ServerEvent, SystemEvent};
+use crate::monitoring::metrics;
+
+pub const DEFAULT_CAPACITY: usize = 10000;
+const DEFAULT_SPILL_PATH: &str = "spill/akari-queue.jsonl";
+// How often a blocked input checks whether the queue has room again
+const BLOCK_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Deserialize)]
+enum SpilledEvent {
+    Server(ServerEvent),
+    System { time: u64, category: String, data: Vec<String> },
+}
+
+// Append-only file of events that didn't fit in the queue, read back in order
+struct SpillFile {
+    path: PathBuf,
+    writer: File,
+    reader: BufReader<File>,
+    pending: usize,
+}
+
+impl SpillFile {
+    fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let path = PathBuf::from(path);
+
+        if let Some(parent) = path.parent() && !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let writer = OpenOptions::new().create(true).append(true).open(&path)?;
+        let pending = BufReader::new(File::open(&path)?).lines().count();
+
+        if pending > 0 {
+            warn!("Replaying {} events spilled to '{}' by a previous run", pending, path.display());
+        }
+
+        metrics::QUEUE_DEPTH.with_label_values(&["spill"]).set(pending as i64);
+
+        Ok(Self { reader: BufReader::new(File::open(&path)?), path, writer, pending })
+    }
+
+    fn write(&mut self, event: &SpilledEvent) -> Result<(), Box<dyn Error>> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        self.writer.write_all(line.as_bytes())?;
+        self.pending += 1;
+        metrics::QUEUE_DEPTH.with_label_values(&["spill"]).set(self.pending as i64);
+
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Option<SpilledEvent>, Box<dyn Error>> {
+        if self.pending == 0 {
+            return Ok(None);
+        }
+
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        self.pending -= 1;
+        metrics::QUEUE_DEPTH.with_label_values(&["spill"]).set(self.pending as i64);
+
+        // Start over with an empty file once everything has been read back
+        if self.pending == 0 {
+            self.writer.set_len(0)?;
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+}
+
+struct QueueState {
+    spill: Option<SpillFile>,
+    full: bool,
+    // Events dropped or spilled since the queue filled up
+    affected: u64,
+}
+
+impl QueueState {
+    fn spilling(&self) -> bool {
+        self.spill.as_ref().is_some_and(|spill| spill.pending > 0)
+    }
+}
+
+// Entry point of the pipeline. Server events are subject to the queue policy once `capacity`
+// events are waiting to be parsed; system events are rare and always go through.
+#[derive(Clone)]
+pub struct EventQueue {
+    sender: UnboundedSender<SequencedEvent>,
+    depth: Arc<AtomicUsize>,
+    capacity: usize,
+    policy: QueuePolicy,
+    state: Arc<Mutex<QueueState>>,
+}
+
+// Handle to the queue that doesn't keep the workers running, for senders that outlive the input
+#[derive(Clone)]
+pub struct WeakEventQueue {
+    sender: WeakUnboundedSender<SequencedEvent>,
+    depth: Arc<AtomicUsize>,
+    capacity: usize,
+    policy: QueuePolicy,
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl WeakEventQueue {
+    // None once the workers have started shutting down
+    pub fn upgrade(&self) -> Option<EventQueue> {
+        Some(EventQueue {
+            sender: self.sender.upgrade()?,
+            depth: self.depth.clone(),
+            capacity: self.capacity,
+            policy: self.policy,
+            state: self.state.clone(),
+        })
+    }
+}
+
+// Receiving end of the queue, closed once every EventQueue is dropped
+pub struct QueueReceiver {
+    receiver: UnboundedReceiver<SequencedEvent>,
+    depth: Arc<AtomicUsize>,
+}
+
+impl QueueReceiver {
+    pub async fn recv(&mut self) -> Option<SequencedEvent> {
+        let event = self.receiver.recv().await?;
+        let depth = self.depth.fetch_sub(1, Ordering::Relaxed) - 1;
+        metrics::QUEUE_DEPTH.with_label_values(&["work"]).set(depth as i64);
+
+        Some(event)
+    }
+
+    pub fn len(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+}
+
+impl EventQueue {
+    pub fn new(config: Option<&QueueConfig>) -> Result<(Self, QueueReceiver), Box<dyn Error>> {
+        let policy = config.and_then(|c| c.policy).unwrap_or_default();
+
+        let spill = match policy {
+            QueuePolicy::Spill => {
+                let path = config.and_then(|c| c.spill_path.as_deref()).unwrap_or(DEFAULT_SPILL_PATH);
+                Some(SpillFile::open(path).map_err(|err| format!("Failed to open spill file '{}': {}", path, err))?)
+            },
+            _ => None,
+        };
+
+        let (sender, receiver) = unbounded_channel();
+        let depth = Arc::new(AtomicUsize::new(0));
+
+        let queue = Self {
+            sender,
+            depth: depth.clone(),
+            capacity: config.and_then(|c| c.capacity).unwrap_or(DEFAULT_CAPACITY),
+            policy,
+            state: Arc::new(Mutex::new(QueueState { spill, full: false, affected: 0 })),
+        };
+
+        Ok((queue, QueueReceiver { receiver, depth }))
+    }
+
+    pub fn downgrade(&self) -> WeakEventQueue {
+        WeakEventQueue {
+            sender: self.sender.downgrade(),
+            depth: self.depth.clone(),
+            capacity: self.capacity,
+            policy: self.policy,
+            state: self.state.clone(),
+        }
+    }
+
+    pub async fn push(&self, event: ServerEvent) {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                self.read_spill(&mut state);
+
+                if !state.spilling() && self.has_room() {
+                    self.send(SequencedEvent::wrap_server(event));
+                    self.set_full(&mut state, false);
+                    return;
+                }
+
+                self.set_full(&mut state, true);
+
+                match self.policy {
+                    QueuePolicy::Block => {},
+                    QueuePolicy::Drop => {
+                        state.affected += 1;
+                        metrics::QUEUE_OVERFLOW.with_label_values(&["dropped"]).inc();
+                        return;
+                    },
+                    QueuePolicy::Spill => {
+                        self.spill(&mut state, SpilledEvent::Server(event));
+                        return;
+                    },
+                }
+            }
+
+            tokio::time::sleep(BLOCK_INTERVAL).await;
+        }
+    }
+
+    pub fn push_system(&self, event: SystemEvent) {
+        let mut state = self.state.lock().unwrap();
+
+        // Keep system events in order with the server events waiting in the spill file
+        if state.spilling() {
+            let SystemEvent { time, category, data } = event;
+            self.spill(&mut state, SpilledEvent::System { time, category: category.to_owned(), data });
+        } else {
+            self.send(SequencedEvent::wrap_system(event));
+        }
+    }
+
+    // Moves spilled events back into the queue if there is room, called when the input is idle
+    pub fn refill(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.read_spill(&mut state);
+
+        if !state.spilling() && self.has_room() {
+            self.set_full(&mut state, false);
+        }
+    }
+
+    // Waits until every spilled event is back in the queue
+    pub async fn drain(&self) {
+        loop {
+            if !self.state.lock().unwrap().spilling() {
+                return;
+            }
+
+            self.refill();
+            tokio::time::sleep(BLOCK_INTERVAL).await;
+        }
+    }
+
+    fn has_room(&self) -> bool {
+        self.depth.load(Ordering::Relaxed) < self.capacity
+    }
+
+    fn send(&self, event: SequencedEvent) {
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        metrics::QUEUE_DEPTH.with_label_values(&["work"]).set(depth as i64);
+
+        if let Err(err) = self.sender.send(event) {
+            self.depth.fetch_sub(1, Ordering::Relaxed);
+            error!("Failed to send event to parser: {err}");
+        }
+    }
+
+    fn spill(&self, state: &mut QueueState, event: SpilledEvent) {
+        let Some(spill) = state.spill.as_mut() else { return };
+
+        match spill.write(&event) {
+            Ok(()) => {
+                state.affected += 1;
+                metrics::QUEUE_OVERFLOW.with_label_values(&["spilled"]).inc();
+            },
+            Err(err) => {
+                error!("Failed to write event to spill file '{}', dropping it: {}", spill.path.display(), err);
+                state.affected += 1;
+                metrics::QUEUE_OVERFLOW.with_label_values(&["dropped"]).inc();
+            },
+        }
+    }
+
+    fn read_spill(&self, state: &mut QueueState) {
+        let Some(spill) = state.spill.as_mut() else { return };
+
+        while spill.pending > 0 && self.has_room() {
+            let event = match spill.read() {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(err) => {
+                    error!("Failed to read event from spill file '{}', skipping it: {}", spill.path.display(), err);
+                    continue;
+                },
+            };
+
+            match event {
+                SpilledEvent::Server(event) => self.send(SequencedEvent::wrap_server(event)),
+                SpilledEvent::System { time, category, data } => {
+                    match SYSTEM_CATEGORIES.iter().find(|known| **known == category) {
+                        Some(category) => self.send(SequencedEvent::wrap_system(SystemEvent { time, category, data })),
+                        None => error!("Skipping spilled system event with unknown category '{}'", category),
+                    }
+                },
+            }
+        }
+    }
+
+    fn set_full(&self, state: &mut QueueState, full: bool) {
+        if state.full == full {
+            return;
+        }
+
+        let policy = self.policy.as_str();
+
+        if full {
+            warn!(policy = policy; "Event queue is full ({} events), applying the '{}' policy", self.capacity, policy);
+            state.affected = 0;
+        } else {
+            info!("Event queue has room again, {} events were dropped or spilled", state.affected);
+        }
+
+        state.full = full;
+
+        // Bypasses the policy, the outputs should hear about this even if the queue is full
+        self.send(SequencedEvent::wrap_system(SystemEvent::backpressure(full, policy, state.affected)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Message;
+    use opentelemetry::Context;
+
+    fn server_event(id: usize) -> ServerEvent {
+        ServerEvent { id: id.to_string(), time: "0".into(), str: String::new(), buckets: vec![], rmb_message: None, received_at: None, context: Context::new() }
+    }
+
+    async fn received(receiver: &mut QueueReceiver) -> Vec<String> {
+        let mut events = Vec::new();
+
+        while receiver.len() > 0 {
+            events.push(match receiver.recv().await.unwrap().get_event() {
+                Message::Server(event) => event.id,
+                Message::System(event) => format!("{}:{}", event.category, event.data.join(",")),
+            });
+        }
+
+        events
+    }
+
+    #[tokio::test]
+    async fn test_spill() {
+        // Unique per process, so concurrent test runs don't share the spill file
+        let path = std::env::temp_dir().join(format!("akari-test-spill-{}.jsonl", std::process::id()));
+
+        let (queue, mut receiver) = EventQueue::new(Some(&QueueConfig {
+            capacity: Some(2),
+            policy: Some(QueuePolicy::Spill),
+            drop_categories: None,
+            spill_path: Some(path.display().to_string()),
+        })).unwrap();
+
+        for id in 0..4 {
+            queue.push(server_event(id)).await;
+        }
+
+        queue.push_system(SystemEvent::shutdown(3));
+        assert_eq!(received(&mut receiver).await, ["0", "1", "backpressure:on,spill,0"]);
+
+        queue.refill();
+        assert_eq!(received(&mut receiver).await, ["2", "3"]);
+
+        queue.drain().await;
+        assert_eq!(received(&mut receiver).await, ["shutdown:3", "backpressure:off,spill,3"]);
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
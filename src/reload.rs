@@ -0,0 +1,93 @@
+use std::{error::Error, time::{Duration, SystemTime}};
+use log::{info, warn};
+use tokio::time::{Interval, MissedTickBehavior, interval};
+
+use crate::config::{Config, load_config};
+use crate::events::SystemEvent;
+use crate::logging;
+use crate::output::diff_outputs;
+use crate::worker::{OutputCommand, Workers};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Polls the config file's modification time, for setups where sending SIGHUP is inconvenient
+pub struct ConfigWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    interval: Interval,
+}
+
+impl ConfigWatcher {
+    pub async fn new(path: &str) -> Self {
+        let mut interval = interval(WATCH_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        ConfigWatcher { path: path.to_string(), modified: modified_time(path).await, interval }
+    }
+
+    // Resolves once the file has been modified since the last call, never if the watcher is disabled
+    pub async fn changed(watcher: &mut Option<ConfigWatcher>) {
+        let Some(watcher) = watcher else {
+            return std::future::pending().await;
+        };
+
+        loop {
+            watcher.interval.tick().await;
+            let modified = modified_time(&watcher.path).await;
+
+            // A missing file is usually an editor replacing it, wait for the new one
+            if modified.is_some() && modified != watcher.modified {
+                watcher.modified = modified;
+                return;
+            }
+        }
+    }
+}
+
+async fn modified_time(path: &str) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.and_then(|metadata| metadata.modified()).ok()
+}
+
+// Reads the config file again and applies output changes without touching the input connection.
+// The running configuration is kept as is if the new one fails to load or validate.
+pub async fn reload_config(path: &str, current: &mut Config, workers: &Workers) -> Result<(), Box<dyn Error>> {
+    let new_config = load_config(path)?;
+    let changes = diff_outputs(current, &new_config).await?;
+
+    for (section, old, new) in [
+        ("input", serde_json::to_value(&current.input)?, serde_json::to_value(&new_config.input)?),
+        ("api", serde_json::to_value(&current.api)?, serde_json::to_value(&new_config.api)?),
+        ("monitoring", serde_json::to_value(&current.monitoring)?, serde_json::to_value(&new_config.monitoring)?),
+        ("shutdown", serde_json::to_value(&current.shutdown)?, serde_json::to_value(&new_config.shutdown)?),
+        ("queue", serde_json::to_value(&current.queue)?, serde_json::to_value(&new_config.queue)?),
+        ("admin", serde_json::to_value(&current.admin)?, serde_json::to_value(&new_config.admin)?),
+        ("tracing", serde_json::to_value(&current.tracing)?, serde_json::to_value(&new_config.tracing)?),
+        ("updates", serde_json::to_value(&current.updates)?, serde_json::to_value(&new_config.updates)?),
+        ("world", serde_json::to_value(&current.world)?, serde_json::to_value(&new_config.world)?),
+    ] {
+        if old != new {
+            warn!("Changes to the [{}] section can't be applied without a restart", section);
+        }
+    }
+
+    logging::configure(new_config.logging.as_ref())?;
+
+    for name in changes.removed.iter().chain(&changes.changed) {
+        workers.outputs.send(OutputCommand::Remove(name.clone()))?;
+    }
+
+    for channel in changes.channels {
+        workers.outputs.send(OutputCommand::Add(channel))?;
+    }
+
+    info!(
+        "Configuration reloaded (added: {:?}, removed: {:?}, changed: {:?})",
+        changes.added, changes.removed, changes.changed
+    );
+
+    workers.events.push_system(SystemEvent::config_reloaded(&changes.added, &changes.removed, &changes.changed));
+
+    current.output = new_config.output;
+
+    Ok(())
+}
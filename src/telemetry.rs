@@ -0,0 +1,77 @@
+use std::{collections::HashMap, error::Error};
+use log::{error, info};
+use opentelemetry::{
+    Context, KeyValue,
+    global::{self, BoxedSpan},
+    trace::{TraceContextExt, Tracer},
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::{Sampler, SdkTracerProvider}};
+
+use crate::config::TracingConfig;
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+const DEFAULT_SERVICE_NAME: &str = "akari";
+const TRACER_NAME: &str = "akari";
+
+// Spans are no-ops until this is called with tracing enabled
+pub fn initialize(config: Option<&TracingConfig>) -> Result<Option<SdkTracerProvider>, Box<dyn Error>> {
+    let Some(tracing_config) = config else {
+        return Ok(None);
+    };
+
+    if !tracing_config.enabled { return Ok(None); }
+
+    let endpoint = tracing_config.endpoint.as_deref().unwrap_or(DEFAULT_ENDPOINT);
+    let exporter = SpanExporter::builder().with_http().with_endpoint(endpoint).build()?;
+
+    // Events received from an upstream trace keep its sampling decision
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(tracing_config.sample_ratio.unwrap_or(1.0))));
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(sampler)
+        .with_resource(Resource::builder()
+            .with_service_name(tracing_config.service_name.clone().unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_owned()))
+            .build())
+        .build();
+
+    global::set_tracer_provider(provider.clone());
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    info!("Exporting traces to {}", endpoint);
+
+    Ok(Some(provider))
+}
+
+// Exports the spans that are still buffered
+pub async fn shutdown(provider: SdkTracerProvider) {
+    match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+        Ok(Ok(())) => {},
+        Ok(Err(err)) => error!("Failed to export remaining traces: {}", err),
+        Err(err) => error!("Failed to export remaining traces: {}", err),
+    }
+}
+
+// Root span of an event's trace, from receipt until the event is queued for parsing
+pub fn receive_span(event_id: &str) -> Context {
+    let tracer = global::tracer(TRACER_NAME);
+    let span = tracer.span_builder("receive")
+        .with_attributes([KeyValue::new("akari.event", event_id.to_owned())])
+        .start(&tracer);
+
+    Context::new().with_span(span)
+}
+
+pub fn start_span(name: &'static str, parent: &Context) -> BoxedSpan {
+    global::tracer(TRACER_NAME).start_with_context(name, parent)
+}
+
+// W3C trace context headers (traceparent, tracestate) of the current span, for downstream consumers
+pub fn trace_headers() -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&Context::current(), &mut headers));
+
+    headers
+}
@@ -0,0 +1,257 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path, sync::RwLock, time::{Duration, Instant}};
+use lazy_static::lazy_static;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::config::UpdatesConfig;
+use crate::events::{ParsedEvent, SystemEvent, now_timestamp};
+
+const DEFAULT_PROGRESS_INTERVAL: u64 = 60;
+const DEFAULT_END_AFTER: u64 = 180;
+const DEFAULT_STATE_PATH: &str = "state/updates.json";
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateKind {
+    Major,
+    Minor,
+}
+
+impl UpdateKind {
+    // Major update starts at midnight New York time and minor update at noon. EST (UTC-5) is used all year,
+    // during daylight saving time updates start an hour earlier in UTC, which is still in the same half of the day.
+    fn from_start(time: u64) -> Self {
+        let hour = OffsetDateTime::from_unix_timestamp(time as i64).map_or(0, |time| time.hour());
+        let new_york_hour = (hour + 24 - 5) % 24;
+
+        if !(6..18).contains(&new_york_hour) { UpdateKind::Major } else { UpdateKind::Minor }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            UpdateKind::Major => "major",
+            UpdateKind::Minor => "minor",
+        }
+    }
+}
+
+// Last update of a region, as NS timestamps
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RegionUpdates {
+    pub major: Option<u64>,
+    pub minor: Option<u64>,
+}
+
+// Start is the time of the first region update, end the time of the last one so far
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UpdateSummary {
+    pub kind: UpdateKind,
+    pub start: u64,
+    pub end: u64,
+    pub regions: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct UpdateState {
+    #[serde(skip)]
+    current: Option<UpdateSummary>,
+    previous: BTreeMap<UpdateKind, UpdateSummary>,
+    regions: BTreeMap<String, RegionUpdates>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateStatus {
+    pub current: Option<UpdateSummary>,
+    pub previous: BTreeMap<UpdateKind, UpdateSummary>,
+}
+
+lazy_static! {
+    static ref STATE: RwLock<UpdateState> = RwLock::new(UpdateState::default());
+}
+
+pub fn status() -> UpdateStatus {
+    let state = STATE.read().unwrap();
+    UpdateStatus { current: state.current.clone(), previous: state.previous.clone() }
+}
+
+pub fn region(name: &str) -> Option<RegionUpdates> {
+    STATE.read().unwrap().regions.get(name).cloned()
+}
+
+// Turns rupdate happenings into updstart, updprog and updend events. Runs in the output worker,
+// which sees events in order and checks every second whether the update has ended.
+pub struct UpdateTracker {
+    progress_interval: Duration,
+    end_after: Duration,
+    state_path: String,
+    // Wall clock times, NS timestamps only have second precision and can lag behind
+    last_seen: Instant,
+    last_progress: Instant,
+}
+
+impl UpdateTracker {
+    pub fn new(config: Option<&UpdatesConfig>) -> Result<Option<Self>, Box<dyn Error>> {
+        let Some(updates_config) = config else {
+            return Ok(None);
+        };
+
+        if !updates_config.enabled { return Ok(None); }
+
+        let state_path = updates_config.state_path.clone().unwrap_or_else(|| DEFAULT_STATE_PATH.to_owned());
+
+        if Path::new(&state_path).exists() {
+            let state: UpdateState = fs::read_to_string(&state_path).map_err(Box::<dyn Error>::from)
+                .and_then(|contents| Ok(serde_json::from_str(&contents)?))
+                .map_err(|err| format!("Failed to read update state from '{}': {}", state_path, err))?;
+
+            info!("Loaded update times of {} regions from '{}'", state.regions.len(), state_path);
+            *STATE.write().unwrap() = state;
+        }
+
+        Ok(Some(Self::with_intervals(
+            Duration::from_secs(updates_config.progress_interval.unwrap_or(DEFAULT_PROGRESS_INTERVAL)),
+            Duration::from_secs(updates_config.end_after.unwrap_or(DEFAULT_END_AFTER)),
+            state_path,
+        )))
+    }
+
+    fn with_intervals(progress_interval: Duration, end_after: Duration, state_path: String) -> Self {
+        Self { progress_interval, end_after, state_path, last_seen: Instant::now(), last_progress: Instant::now() }
+    }
+
+    // Returns updstart if this is the first region of an update, to be output before the event itself
+    pub fn observe(&mut self, event: &ParsedEvent) -> Option<SystemEvent> {
+        if event.category != "rupdate" {
+            return None;
+        }
+
+        let Some(region) = &event.origin else { return None };
+
+        let mut state = STATE.write().unwrap();
+        let mut started = None;
+
+        let current = state.current.get_or_insert_with(|| {
+            let kind = UpdateKind::from_start(event.time);
+            info!("Update started ({})", kind.as_str());
+
+            self.last_progress = Instant::now();
+            started = Some(SystemEvent::update_started(event.time, kind.as_str()));
+
+            UpdateSummary { kind, start: event.time, end: event.time, regions: 0 }
+        });
+
+        current.regions += 1;
+        current.end = event.time;
+        let kind = current.kind;
+
+        let times = state.regions.entry(region.clone()).or_default();
+
+        match kind {
+            UpdateKind::Major => times.major = Some(event.time),
+            UpdateKind::Minor => times.minor = Some(event.time),
+        }
+
+        self.last_seen = Instant::now();
+
+        started
+    }
+
+    // Returns updend once no region has updated for `end_after`, or updprog every `progress_interval`
+    pub fn tick(&mut self) -> Option<SystemEvent> {
+        let mut state = STATE.write().unwrap();
+        let current = state.current.as_ref()?;
+
+        if self.last_seen.elapsed() >= self.end_after {
+            let current = state.current.take()?;
+            info!(
+                "Update ended ({}), {} regions updated in {} seconds", current.kind.as_str(), current.regions, current.end - current.start
+            );
+
+            let event = SystemEvent::update_ended(current.end, current.kind.as_str(), current.regions, current.end - current.start);
+            state.previous.insert(current.kind, current);
+            drop(state);
+
+            self.save();
+            return Some(event);
+        }
+
+        if self.last_progress.elapsed() >= self.progress_interval {
+            self.last_progress = Instant::now();
+            let expected = state.previous.get(&current.kind).map_or(0, |previous| previous.regions);
+
+            return Some(SystemEvent::update_progress(
+                current.kind.as_str(), current.regions, expected, now_timestamp().saturating_sub(current.start)
+            ));
+        }
+
+        None
+    }
+
+    // Called after each update and on shutdown
+    pub fn save(&self) {
+        let result = serde_json::to_string(&*STATE.read().unwrap()).map_err(Box::<dyn Error>::from).and_then(|contents| {
+            if let Some(parent) = Path::new(&self.state_path).parent() && !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // Written to a temporary file first, so a crash can't leave a truncated state behind
+            let temporary = format!("{}.tmp", self.state_path);
+            fs::write(&temporary, contents)?;
+            fs::rename(&temporary, &self.state_path)?;
+
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            error!("Failed to save update state to '{}': {}", self.state_path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_kind() {
+        // 2024-01-15 05:00 UTC (midnight EST) and 2024-07-15 16:00 UTC (noon EDT)
+        assert_eq!(UpdateKind::from_start(1705294800), UpdateKind::Major);
+        assert_eq!(UpdateKind::from_start(1721059200), UpdateKind::Minor);
+    }
+
+    #[test]
+    fn test_update_events() {
+        let state_path = std::env::temp_dir().join(format!("akari-test-updates-{}.json", std::process::id()));
+        let mut tracker = UpdateTracker::with_intervals(Duration::ZERO, Duration::from_secs(3600), state_path.display().to_string());
+
+        let rupdate = |id, time, region: &str| {
+            let mut event = ParsedEvent::new(id, time, "rupdate");
+            event.origin = Some(region.to_owned());
+            event
+        };
+
+        // 2024-01-15 05:00 UTC, major update
+        let started = tracker.observe(&rupdate(1, 1705294800, "the_pacific")).unwrap();
+        assert_eq!((started.category, started.data), ("updstart", vec!["major".to_owned()]));
+        assert!(tracker.observe(&rupdate(2, 1705294830, "lazarus")).is_none());
+        assert!(tracker.observe(&ParsedEvent::new(3, 1705294831, "move")).is_none());
+
+        let progress = tracker.tick().unwrap();
+        assert_eq!(progress.category, "updprog");
+        assert_eq!(progress.data[..3], ["major", "2", "0"]);
+
+        tracker.end_after = Duration::ZERO;
+        let ended = tracker.tick().unwrap();
+        assert_eq!((ended.time, ended.category), (1705294830, "updend"));
+        assert_eq!(ended.data, ["major", "2", "30"]);
+        assert!(tracker.tick().is_none());
+
+        assert_eq!(region("the_pacific").and_then(|times| times.major), Some(1705294800));
+        assert_eq!(region("lazarus").and_then(|times| times.major), Some(1705294830));
+        assert!(status().current.is_none());
+        assert_eq!(status().previous[&UpdateKind::Major].regions, 2);
+
+        fs::remove_file(state_path).unwrap();
+    }
+}
@@ -1,82 +1,371 @@
-use crossbeam::channel::{unbounded, Sender};
+use futures_util::{StreamExt, stream::FuturesUnordered};
 use log::error;
-use std::{collections::BTreeMap, thread};
-use tokio::runtime::Runtime;
+use opentelemetry::{Context, KeyValue, trace::{FutureExt, Span, Status, TraceContextExt}};
+use std::{collections::{BTreeMap, HashSet}, error::Error, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, time::Duration};
+use tokio::{sync::{mpsc, oneshot}, task::{self, JoinHandle}};
 
-use crate::events::{Message, ParsedEvent, SequencedEvent};
-use crate::output::{process_outputs, OutputChannel};
+use crate::config::{Config, QueuePolicy};
+use crate::events::{Message, ParsedEvent, SequencedEvent, now_timestamp, now_timestamp_millis};
+use crate::monitoring::{health, metrics};
+use crate::output::{FilterSummary, OutputChannel, OutputChannelFilter, output_event, resolve_categories, shutdown_output};
 use crate::parser::EventParser;
+use crate::queue::{DEFAULT_CAPACITY, EventQueue, QueueReceiver};
+use crate::telemetry;
+use crate::updates::UpdateTracker;
+use crate::world::WorldTracker;
 
-fn broadcast_event(
-    outputs: &mut Vec<Box<dyn OutputChannel>>,
-    rt: &Runtime,
+// Changes to the set of outputs, applied by the output worker between two events
+pub enum OutputCommand {
+    Add(Box<dyn OutputChannel>),
+    Remove(String),
+    // Lists the running outputs, for the admin API
+    List(oneshot::Sender<Vec<OutputStatus>>),
+}
+
+// Runtime state of an output, shared between its task and the admin API
+pub struct OutputControl {
+    pub name: String,
+    paused: AtomicBool,
+    filter: Mutex<FilterSummary>,
+    // Picked up by the output task before its next event
+    new_filter: Mutex<Option<OutputChannelFilter>>,
+}
+
+impl OutputControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn filter(&self) -> FilterSummary {
+        self.filter.lock().unwrap().clone()
+    }
+
+    pub fn set_filter(&self, filter: OutputChannelFilter) {
+        *self.filter.lock().unwrap() = FilterSummary::from(&filter);
+        *self.new_filter.lock().unwrap() = Some(filter);
+    }
+}
+
+pub struct OutputStatus {
+    pub control: Arc<OutputControl>,
+    // Events waiting in the output's own queue
+    pub queued: usize,
+}
+
+pub struct Workers {
+    pub events: EventQueue,
+    pub outputs: mpsc::UnboundedSender<OutputCommand>,
+    // Finishes after all queued events have been output and the outputs have been shut down
+    pub output_task: JoinHandle<()>,
+}
+
+// A parsed event, with the trace it was received in
+struct TracedEvent {
     event: ParsedEvent,
-) {
-    rt.block_on(async {
-        if let Err(err) = process_outputs(outputs, event).await {
-            error!("Error while processing output: {}", err);
-        }
-    });
+    context: Context,
 }
 
-pub fn spawn_work_threads(mut outputs: Vec<Box<dyn OutputChannel>>, worker_count: usize) 
-    -> Sender<SequencedEvent>
-{
-    let (work_tx, work_rx) = unbounded::<SequencedEvent>();
-    let (result_tx, result_rx) = unbounded::<(usize, Option<ParsedEvent>)>();
-
-    // Spawn parser workers
-    for _ in 0..worker_count {
-        let tx = result_tx.clone();
-        let rx = work_rx.clone();
-        let parser = EventParser::new().expect("Failed to create event parser for worker thread");
-
-        thread::spawn(move || {
-            for msg in rx {
-                let seq_id = msg.sequence_id();
-
-                match msg.get_event() {
-                    Message::Server(event) => {
-                        let result = parser.parse_server_event(event);
-                        tx.send((seq_id, result)).unwrap_or_else(|err| {
-                            error!("Failed to send parsed event to output worker: {}", err);
-                        });
-                    },
-                    Message::System(mut event) => {
-                        let mut result = ParsedEvent::new(
-                            -1, 
-                            event.time, 
-                            event.category
-                        );
-
-                        result.data = std::mem::take(&mut event.data);
-                        tx.send((seq_id, Some(result))).unwrap_or_else(|err| {
-                            error!("Failed to send parsed event to output worker: {}", err);
-                        });
-                    }
+// Each output runs as its own task, so a slow output only holds back the others once its queue is full
+struct OutputTask {
+    name: String,
+    events: mpsc::Sender<Arc<TracedEvent>>,
+    handle: JoinHandle<()>,
+    control: Arc<OutputControl>,
+}
+
+impl OutputTask {
+    fn spawn(mut channel: Box<dyn OutputChannel>, capacity: usize) -> Self {
+        let (events, mut receiver) = mpsc::channel::<Arc<TracedEvent>>(capacity);
+        let name = channel.name().to_owned();
+
+        let control = Arc::new(OutputControl {
+            name: name.clone(),
+            paused: AtomicBool::new(false),
+            filter: Mutex::new(FilterSummary::from(channel.get_filter())),
+            new_filter: Mutex::new(None),
+        });
+
+        let task_control = control.clone();
+
+        let handle = tokio::spawn(async move {
+            while let Some(traced) = receiver.recv().await {
+                let event = &traced.event;
+                let new_filter = task_control.new_filter.lock().unwrap().take();
+
+                if let Some(filter) = new_filter {
+                    channel.set_filter(filter);
+                }
+
+                if !channel.get_filter().should_output_event(event) {
+                    continue;
                 }
+
+                // Paused outputs skip events instead of holding them, so they can't back up the other outputs
+                if task_control.is_paused() {
+                    metrics::OUTPUT_EVENTS.with_label_values(&[channel.name(), "paused"]).inc();
+                    continue;
+                }
+
+                let mut span = telemetry::start_span("output", &traced.context);
+                span.set_attribute(KeyValue::new("akari.output", channel.name().to_owned()));
+                let context = traced.context.with_span(span);
+
+                // Outputs can pass the current trace context on to their consumers
+                if let Err(err) = output_event(&mut channel, event).with_context(context.clone()).await {
+                    context.span().set_status(Status::error(err.to_string()));
+
+                    error!(
+                        output = channel.name(), event = event.event, category = event.category.as_str(), error_kind = "output";
+                        "Error while processing output '{}': {}", channel.name(), err
+                    );
+                }
+
+                context.span().end();
             }
+
+            shutdown_output(&mut channel).await;
         });
+
+        Self { name, events, handle, control }
     }
 
-    // Spawn output worker
-    thread::spawn(move || {
-        let mut next_sequence_id = 0;
-        let mut buffer = BTreeMap::new();
-        let rt = Runtime::new().expect("Failed to initialize Tokio runtime for output worker thread");
+    // Lets the output finish its queued events, then shuts it down
+    async fn stop(self) {
+        drop(self.events);
 
-        for (i, result) in &result_rx {
-            buffer.insert(i, result);
+        if let Err(err) = self.handle.await {
+            error!("Output '{}' stopped unexpectedly: {}", self.name, err);
+        }
+    }
+}
+
+// Workers stop once every copy of the event queue is dropped
+pub async fn spawn_workers(outputs: Vec<Box<dyn OutputChannel>>, config: &Config) -> Result<Workers, Box<dyn Error>> {
+    let capacity = config.queue.as_ref().and_then(|q| q.capacity).unwrap_or(DEFAULT_CAPACITY);
+
+    // Low priority categories, discarded while the work queue is more than half full
+    let shed_categories: HashSet<String> = match &config.queue {
+        Some(queue) if queue.policy == Some(QueuePolicy::Drop) => {
+            queue.drop_categories.as_ref().map(resolve_categories).transpose()?.unwrap_or_default()
+        },
+        _ => HashSet::new(),
+    };
+
+    let (events, receiver) = EventQueue::new(config.queue.as_ref())?;
+    let (command_tx, command_rx) = mpsc::unbounded_channel::<OutputCommand>();
+    let (result_tx, result_rx) = mpsc::channel::<TracedEvent>(capacity);
+    let parser = Arc::new(EventParser::new()?);
+    let tracker = UpdateTracker::new(config.updates.as_ref())?;
+    // Parsing the dumps can take a while, so it runs on the blocking thread pool
+    let world_config = config.world.clone();
+    let world = tokio::task::spawn_blocking(move || WorldTracker::new(world_config.as_ref()).map_err(|err| err.to_string())).await??;
+
+    tokio::spawn(parse_events(receiver, parser, config.input.workers, capacity, shed_categories, result_tx));
+    let output_task = tokio::spawn(output_events(outputs, capacity, tracker, world, result_rx, command_rx));
+
+    Ok(Workers { events, outputs: command_tx, output_task })
+}
+
+// Parses up to `workers` events at a time on the blocking thread pool, and passes the results on
+// in sequence ID order. Events that finish early wait in a buffer that can't outgrow `workers`.
+async fn parse_events(
+    mut receiver: QueueReceiver,
+    parser: Arc<EventParser>,
+    workers: usize,
+    capacity: usize,
+    shed_categories: HashSet<String>,
+    results: mpsc::Sender<TracedEvent>,
+) {
+    let heartbeat = health::register_worker("parser");
+    let shed_categories = Arc::new(shed_categories);
+    let mut pending = FuturesUnordered::new();
+    let mut buffer = BTreeMap::new();
+    let mut next_sequence_id = None;
+    let mut input_open = true;
+
+    loop {
+        tokio::select! {
+            event = receiver.recv(), if input_open && pending.len() + buffer.len() < workers => match event {
+                Some(event) => {
+                    if pending.is_empty() && buffer.is_empty() {
+                        heartbeat.busy();
+                    }
 
-            while let Some(maybe_event) = buffer.remove(&next_sequence_id) {
-                if let Some(event) = maybe_event {
-                    broadcast_event(&mut outputs, &rt, event);
+                    next_sequence_id.get_or_insert(event.sequence_id());
+
+                    let shed = (receiver.len() >= capacity / 2).then(|| shed_categories.clone());
+                    pending.push(parse_event(parser.clone(), event, shed));
+                },
+                None => input_open = false,
+            },
+            Some((seq_id, result, context)) = pending.next() => {
+                // Time spent waiting for earlier events to be parsed
+                let reorder = telemetry::start_span("reorder", &context);
+                buffer.insert(seq_id, (result, context, reorder));
+
+                while let Some(next) = next_sequence_id.as_mut() && let Some((result, context, mut reorder)) = buffer.remove(next) {
+                    *next += 1;
+                    reorder.end();
+
+                    if let Some(event) = result && results.send(TracedEvent { event, context }).await.is_err() {
+                        error!("Failed to send parsed event to output worker");
+                    }
                 }
 
-                next_sequence_id += 1;
+                // Busy means no progress has been made since that time
+                if pending.is_empty() && buffer.is_empty() { heartbeat.idle() } else { heartbeat.busy() }
+            },
+            else => break,
+        }
+    }
+}
+
+async fn parse_event(
+    parser: Arc<EventParser>, event: SequencedEvent, shed: Option<Arc<HashSet<String>>>
+) -> (usize, Option<ParsedEvent>, Context) {
+    let seq_id = event.sequence_id();
+
+    let (result, context) = match event.get_event() {
+        Message::Server(event) => {
+            let context = event.context.clone();
+            let received_at = event.received_at;
+            let mut span = telemetry::start_span("parse", &context);
+
+            let parsed = task::spawn_blocking(move || {
+                let timer = metrics::PARSE_DURATION.start_timer();
+                let result = parser.parse_server_event(event);
+                timer.observe_duration();
+
+                result
+            }).await.unwrap_or_else(|err| {
+                error!(error_kind = "parse"; "Parser task failed: {}", err);
+                None
+            });
+
+            let parsed = parsed.map(|mut parsed| {
+                parsed.received_at = received_at;
+                parsed.parsed_at = Some(now_timestamp_millis());
+                parsed
+            });
+
+            match &parsed {
+                Some(parsed) => {
+                    metrics::EVENTS_PARSED.with_label_values(&[&parsed.category]).inc();
+                    span.set_attribute(KeyValue::new("akari.category", parsed.category.clone()));
+                },
+                None => metrics::EVENTS_DISCARDED.inc(),
             }
+
+            span.end();
+
+            // The sequence ID still has to reach the output worker, so shed events are passed on as None
+            if let Some(shed) = shed && parsed.as_ref().is_some_and(|parsed| shed.contains(&parsed.category)) {
+                metrics::QUEUE_OVERFLOW.with_label_values(&["shed"]).inc();
+                (None, context)
+            } else {
+                (parsed, context)
+            }
+        },
+        Message::System(event) => (Some(ParsedEvent::from(event)), Context::new()),
+    };
+
+    (seq_id, result, context)
+}
+
+async fn output_events(
+    outputs: Vec<Box<dyn OutputChannel>>,
+    capacity: usize,
+    mut tracker: Option<UpdateTracker>,
+    mut world: Option<WorldTracker>,
+    mut results: mpsc::Receiver<TracedEvent>,
+    mut commands: mpsc::UnboundedReceiver<OutputCommand>,
+) {
+    let heartbeat = health::register_worker("output");
+    let mut tasks: Vec<OutputTask> = outputs.into_iter().map(|channel| OutputTask::spawn(channel, capacity)).collect();
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        tokio::select! {
+            biased;
+
+            Some(command) = commands.recv() => match command {
+                OutputCommand::Add(channel) => tasks.push(OutputTask::spawn(channel, capacity)),
+                OutputCommand::Remove(name) => {
+                    for task in tasks.extract_if(.., |task| task.name == name) {
+                        task.stop().await;
+                    }
+
+                    health::remove_output(&name);
+                },
+                OutputCommand::List(reply) => {
+                    let _ = reply.send(tasks.iter().map(|task| OutputStatus {
+                        control: task.control.clone(),
+                        queued: task.events.max_capacity() - task.events.capacity(),
+                    }).collect());
+                },
+            },
+            _ = ticker.tick(), if tracker.is_some() || world.is_some() => {
+                if let Some(world) = world.as_mut() {
+                    world.tick();
+                }
+
+                if let Some(event) = tracker.as_mut().and_then(UpdateTracker::tick) {
+                    send_to_outputs(&tasks, Arc::new(TracedEvent { event: event.into(), context: Context::new() })).await;
+                }
+            },
+            event = results.recv() => {
+                let Some(mut traced) = event else { break };
+                metrics::QUEUE_DEPTH.with_label_values(&["result"]).set(results.len() as i64);
+
+                heartbeat.busy();
+
+                if let Some(world) = &world {
+                    world.infer_origin(&mut traced.event);
+                }
+
+                let traced = Arc::new(traced);
+
+                if let Some(event) = tracker.as_mut().and_then(|tracker| tracker.observe(&traced.event)) {
+                    send_to_outputs(&tasks, Arc::new(TracedEvent { event: event.into(), context: Context::new() })).await;
+                }
+
+                send_to_outputs(&tasks, traced.clone()).await;
+
+                if let Some(world) = world.as_mut() {
+                    world.observe(&traced.event);
+                }
+
+                heartbeat.idle();
+
+                // System events have no NS timestamp to measure lag from
+                if traced.event.event >= 0 {
+                    metrics::EVENT_LAG.observe(now_timestamp().saturating_sub(traced.event.time) as f64);
+                }
+            },
         }
-    });
+    }
+
+    if let Some(tracker) = tracker {
+        tracker.save();
+    }
+
+    if let Some(world) = world {
+        world.save();
+    }
 
-    work_tx
-}
\ No newline at end of file
+    for task in tasks {
+        task.stop().await;
+    }
+}
+
+async fn send_to_outputs(tasks: &[OutputTask], event: Arc<TracedEvent>) {
+    for task in tasks {
+        if task.events.send(event.clone()).await.is_err() {
+            error!("Output '{}' is no longer running", task.name);
+        }
+    }
+}
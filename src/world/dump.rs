@@ -0,0 +1,135 @@
+use std::{error::Error, fs::File, io::{BufRead, BufReader}};
+use flate2::bufread::GzDecoder;
+use quick_xml::{Reader, escape::resolve_predefined_entity, events::Event};
+
+// A nation from nations.xml, names normalized
+pub struct DumpNation {
+    pub name: String,
+    pub region: String,
+    pub wa: bool,
+    pub endorsements: Vec<String>,
+}
+
+// A region from regions.xml, names normalized
+pub struct DumpRegion {
+    pub name: String,
+    pub delegate: Option<String>,
+    pub founder: Option<String>,
+}
+
+// Dumps use display names in some places and lowercase names with underscores in others
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "_")
+}
+
+// NS uses "0" for regions without a delegate or founder
+fn optional_name(name: &str) -> Option<String> {
+    let name = normalize(name);
+    (!name.is_empty() && name != "0").then_some(name)
+}
+
+fn open(path: &str) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    let file = BufReader::new(File::open(path).map_err(|err| format!("Failed to open dump '{}': {}", path, err))?);
+
+    // The daily dumps are published gzipped, but may have been extracted already
+    if path.ends_with(".gz") {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+pub fn read_nations(path: &str, mut handle: impl FnMut(DumpNation)) -> Result<(), Box<dyn Error>> {
+    read_records(open(path)?, "NATION", ["NAME", "REGION", "UNSTATUS", "ENDORSEMENTS"], |[name, region, status, endorsements]| {
+        handle(DumpNation {
+            name: normalize(&name),
+            region: normalize(&region),
+            // "WA Member" or "WA Delegate", otherwise "Non-member"
+            wa: status.starts_with("WA "),
+            endorsements: endorsements.split(',').filter(|name| !name.is_empty()).map(normalize).collect(),
+        });
+    })
+}
+
+pub fn read_regions(path: &str, mut handle: impl FnMut(DumpRegion)) -> Result<(), Box<dyn Error>> {
+    read_records(open(path)?, "REGION", ["NAME", "DELEGATE", "FOUNDER"], |[name, delegate, founder]| {
+        handle(DumpRegion {
+            name: normalize(&name),
+            delegate: optional_name(&delegate),
+            founder: optional_name(&founder),
+        });
+    })
+}
+
+// Streams the dump, calling `handle` with the text of `fields` for every `record` element under the root.
+// Dumps are too large to be loaded at once, and nested elements (GOVT, DEATHS, ...) are skipped.
+fn read_records<const N: usize>(
+    reader: impl BufRead, record: &str, fields: [&str; N], mut handle: impl FnMut([String; N])
+) -> Result<(), Box<dyn Error>> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut values: [String; N] = std::array::from_fn(|_| String::new());
+    let mut depth = 0;
+    let mut field = None;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(element) => {
+                depth += 1;
+
+                if depth == 3 {
+                    field = fields.iter().position(|name| name.as_bytes() == element.name().as_ref());
+                }
+            },
+            Event::End(element) => {
+                if depth == 2 && element.name().as_ref() == record.as_bytes() {
+                    handle(std::mem::replace(&mut values, std::array::from_fn(|_| String::new())));
+                }
+
+                depth -= 1;
+                field = None;
+            },
+            Event::Text(text) => if depth == 3 && let Some(index) = field {
+                values[index].push_str(&text.decode()?);
+            },
+            Event::GeneralRef(reference) => if depth == 3 && let Some(index) = field {
+                if let Some(character) = reference.resolve_char_ref()? {
+                    values[index].push(character);
+                } else if let Some(entity) = resolve_predefined_entity(&reference.decode()?) {
+                    values[index].push_str(entity);
+                }
+            },
+            Event::Eof => break,
+            _ => {},
+        }
+
+        buf.clear();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_records() {
+        let dump = r#"<?xml version="1.0" encoding="UTF-8"?>
+<NATIONS>
+<NATION><NAME>Testlandia</NAME><UNSTATUS>WA Delegate</UNSTATUS><GOVT><ADMINISTRATION>1.0</ADMINISTRATION></GOVT><REGION>Testregia &amp; Co</REGION><ENDORSEMENTS>a,b_c</ENDORSEMENTS></NATION>
+<NATION><NAME>Other Nation</NAME><UNSTATUS>Non-member</UNSTATUS><REGION>The Pacific</REGION><ENDORSEMENTS></ENDORSEMENTS></NATION>
+</NATIONS>"#;
+
+        let mut records = Vec::new();
+        read_records(dump.as_bytes(), "NATION", ["NAME", "REGION", "UNSTATUS", "ENDORSEMENTS"], |record| records.push(record)).unwrap();
+
+        assert_eq!(records, vec![
+            ["Testlandia", "Testregia & Co", "WA Delegate", "a,b_c"].map(String::from),
+            ["Other Nation", "The Pacific", "Non-member", ""].map(String::from),
+        ]);
+
+        assert_eq!(optional_name("0"), None);
+        assert_eq!(optional_name("Some Nation"), Some("some_nation".into()));
+    }
+}
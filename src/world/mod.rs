@@ -0,0 +1,444 @@
+use std::{
+    collections::{BTreeSet, HashMap}, error::Error, fs, path::Path, sync::{Mutex, RwLock, atomic::{AtomicBool, Ordering}}, time::{Duration, Instant, SystemTime}
+};
+use lazy_static::lazy_static;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::config::WorldConfig;
+use crate::events::{ParsedEvent, UNKNOWN_ORIGIN};
+
+mod dump;
+
+const DEFAULT_SNAPSHOT_PATH: &str = "state/world.json";
+const DEFAULT_SNAPSHOT_INTERVAL: u64 = 300;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct NationState {
+    pub region: String,
+    pub wa: bool,
+    // Nations endorsing this one
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub endorsements: BTreeSet<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RegionState {
+    pub delegate: Option<String>,
+    pub founder: Option<String>,
+    // Rebuilt from the nations when a snapshot is loaded
+    #[serde(skip)]
+    pub nations: BTreeSet<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct WorldState {
+    // NS timestamp of the last event applied
+    updated: Option<u64>,
+    nations: HashMap<String, NationState>,
+    regions: HashMap<String, RegionState>,
+}
+
+#[derive(Serialize)]
+pub struct WorldSummary {
+    pub enabled: bool,
+    pub updated: Option<u64>,
+    pub nations: usize,
+    pub regions: usize,
+    pub wa_members: usize,
+}
+
+#[derive(Serialize)]
+pub struct NationInfo {
+    pub name: String,
+    pub region: String,
+    pub wa: bool,
+    pub delegate: bool,
+    pub endorsements: BTreeSet<String>,
+    // Nations this one endorses
+    pub endorsing: BTreeSet<String>,
+}
+
+#[derive(Serialize)]
+pub struct RegionInfo {
+    pub name: String,
+    pub delegate: Option<String>,
+    pub founder: Option<String>,
+    pub nations: BTreeSet<String>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref STATE: RwLock<WorldState> = RwLock::new(WorldState::default());
+    // Held while a snapshot is written, so two snapshots never write the same file
+    static ref SNAPSHOT_LOCK: Mutex<()> = Mutex::new(());
+}
+
+pub fn summary() -> WorldSummary {
+    let state = STATE.read().unwrap();
+
+    WorldSummary {
+        enabled: ENABLED.load(Ordering::Relaxed),
+        updated: state.updated,
+        nations: state.nations.len(),
+        regions: state.regions.len(),
+        wa_members: state.nations.values().filter(|nation| nation.wa).count(),
+    }
+}
+
+pub fn nation(name: &str) -> Option<NationInfo> {
+    let state = STATE.read().unwrap();
+    let nation = state.nations.get(name)?;
+    let region = state.regions.get(&nation.region);
+
+    // Endorsements can only be given within a region
+    let endorsing = region.map(|region| {
+        region.nations.iter()
+            .filter(|other| state.nations.get(*other).is_some_and(|other| other.endorsements.contains(name)))
+            .cloned().collect()
+    }).unwrap_or_default();
+
+    Some(NationInfo {
+        name: name.to_owned(),
+        region: nation.region.clone(),
+        wa: nation.wa,
+        delegate: region.is_some_and(|region| region.delegate.as_deref() == Some(name)),
+        endorsements: nation.endorsements.clone(),
+        endorsing,
+    })
+}
+
+pub fn region(name: &str) -> Option<RegionInfo> {
+    let state = STATE.read().unwrap();
+    let region = state.regions.get(name)?;
+
+    Some(RegionInfo {
+        name: name.to_owned(),
+        delegate: region.delegate.clone(),
+        founder: region.founder.clone(),
+        nations: region.nations.clone(),
+    })
+}
+
+impl WorldState {
+    fn apply(&mut self, event: &ParsedEvent) {
+        let actor = event.actor.as_deref();
+        let receptor = event.receptor.as_deref();
+        let origin = event.origin.as_deref();
+
+        match (event.category.as_str(), actor, receptor, origin) {
+            ("move", Some(nation), _, _) => if let Some(destination) = &event.destination {
+                self.move_nation(nation, destination);
+            },
+            ("nfound" | "nrefound", Some(nation), _, Some(region)) => {
+                self.remove_nation(nation);
+                self.move_nation(nation, region);
+            },
+            ("ncte", _, Some(nation), _) => self.remove_nation(nation),
+            ("wadmit", Some(nation), _, _) => if let Some(state) = self.nation_mut(nation, origin) {
+                state.wa = true;
+            },
+            ("wresign" | "wkick", Some(nation), _, _) => self.leave_wa(nation),
+            ("wendo", Some(endorser), Some(endorsed), _) => if let Some(state) = self.nation_mut(endorsed, origin) {
+                state.endorsements.insert(endorser.to_owned());
+            },
+            ("wunendo", Some(endorser), Some(endorsed), _) => if let Some(state) = self.nations.get_mut(endorsed) {
+                state.endorsements.remove(endorser);
+            },
+            ("ndel" | "rdel", _, Some(nation), Some(region)) => {
+                self.regions.entry(region.to_owned()).or_default().delegate = Some(nation.to_owned());
+            },
+            ("ldel", _, Some(nation), Some(region)) => if let Some(state) = self.regions.get_mut(region)
+                && state.delegate.as_deref() == Some(nation) {
+                state.delegate = None;
+            },
+            ("rfound", Some(nation), _, Some(region)) => {
+                self.regions.entry(region.to_owned()).or_default().founder = Some(nation.to_owned());
+            },
+            ("rfcte", _, Some(nation), Some(region)) => if let Some(state) = self.regions.get_mut(region)
+                && state.founder.as_deref() == Some(nation) {
+                state.founder = None;
+            },
+            _ => return,
+        }
+
+        self.updated = Some(event.time);
+    }
+
+    // Region of the nation behind the event, or of the nation it targets
+    fn nation_region(&self, event: &ParsedEvent) -> Option<String> {
+        [&event.actor, &event.receptor].into_iter().flatten()
+            .find_map(|name| self.nations.get(name))
+            .map(|nation| nation.region.clone())
+    }
+
+    // Nations missing from the dump are added once an event tells which region they're in
+    fn nation_mut(&mut self, name: &str, region: Option<&str>) -> Option<&mut NationState> {
+        if !self.nations.contains_key(name) {
+            self.move_nation(name, region.filter(|region| *region != UNKNOWN_ORIGIN)?);
+        }
+
+        self.nations.get_mut(name)
+    }
+
+    fn move_nation(&mut self, name: &str, destination: &str) {
+        // Moving out of a region drops every endorsement given and received, and the delegacy
+        self.clear_endorsements(name);
+        self.leave_region(name);
+
+        let nation = self.nations.entry(name.to_owned()).or_default();
+        nation.region = destination.to_owned();
+        self.regions.entry(destination.to_owned()).or_default().nations.insert(name.to_owned());
+    }
+
+    fn remove_nation(&mut self, name: &str) {
+        self.clear_endorsements(name);
+        self.leave_region(name);
+        self.nations.remove(name);
+    }
+
+    fn leave_wa(&mut self, name: &str) {
+        self.clear_endorsements(name);
+
+        if let Some(nation) = self.nations.get_mut(name) {
+            nation.wa = false;
+
+            if let Some(region) = self.regions.get_mut(&nation.region) && region.delegate.as_deref() == Some(name) {
+                region.delegate = None;
+            }
+        }
+    }
+
+    fn leave_region(&mut self, name: &str) {
+        let Some(nation) = self.nations.get(name) else { return };
+
+        if let Some(region) = self.regions.get_mut(&nation.region) {
+            region.nations.remove(name);
+
+            if region.delegate.as_deref() == Some(name) {
+                region.delegate = None;
+            }
+        }
+    }
+
+    fn clear_endorsements(&mut self, name: &str) {
+        let Some(nation) = self.nations.get_mut(name) else { return };
+        nation.endorsements.clear();
+
+        let region = nation.region.clone();
+
+        if let Some(region) = self.regions.get(&region) {
+            for other in &region.nations {
+                if let Some(other) = self.nations.get_mut(other) {
+                    other.endorsements.remove(name);
+                }
+            }
+        }
+    }
+
+    fn rebuild_members(&mut self) {
+        for (name, nation) in &self.nations {
+            self.regions.entry(nation.region.clone()).or_default().nations.insert(name.clone());
+        }
+    }
+
+    fn load_dumps(nations_dump: Option<&str>, regions_dump: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let mut state = WorldState::default();
+
+        if let Some(path) = regions_dump {
+            dump::read_regions(path, |region| {
+                state.regions.insert(region.name, RegionState { delegate: region.delegate, founder: region.founder, ..Default::default() });
+            })?;
+        }
+
+        if let Some(path) = nations_dump {
+            dump::read_nations(path, |nation| {
+                state.nations.insert(nation.name, NationState {
+                    region: nation.region, wa: nation.wa, endorsements: nation.endorsements.into_iter().collect(),
+                });
+            })?;
+        }
+
+        state.rebuild_members();
+
+        Ok(state)
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Applies events to the world state in the output worker, which sees them in order,
+// and writes a snapshot every `snapshot_interval`
+pub struct WorldTracker {
+    snapshot_path: String,
+    snapshot_interval: Duration,
+    last_snapshot: Instant,
+    infer_origin: bool,
+}
+
+impl WorldTracker {
+    pub fn new(config: Option<&WorldConfig>) -> Result<Option<Self>, Box<dyn Error>> {
+        let Some(world_config) = config else {
+            return Ok(None);
+        };
+
+        if !world_config.enabled { return Ok(None); }
+
+        let snapshot_path = world_config.snapshot_path.clone().unwrap_or_else(|| DEFAULT_SNAPSHOT_PATH.to_owned());
+        let nations_dump = world_config.nations_dump.as_deref();
+        let regions_dump = world_config.regions_dump.as_deref();
+
+        // A snapshot is more recent than the dumps it was seeded from, unless new dumps have been downloaded since
+        let dumps_modified = nations_dump.into_iter().chain(regions_dump).filter_map(modified).max();
+        let use_snapshot = modified(&snapshot_path).is_some_and(|snapshot| dumps_modified.is_none_or(|dumps| snapshot > dumps));
+
+        let state = if use_snapshot {
+            let mut state: WorldState = fs::read_to_string(&snapshot_path).map_err(Box::<dyn Error>::from)
+                .and_then(|contents| Ok(serde_json::from_str(&contents)?))
+                .map_err(|err| format!("Failed to read world snapshot from '{}': {}", snapshot_path, err))?;
+
+            state.rebuild_members();
+            info!("Loaded {} nations and {} regions from '{}'", state.nations.len(), state.regions.len(), snapshot_path);
+            state
+        } else if nations_dump.is_some() || regions_dump.is_some() {
+            info!("Loading world state from the daily dumps, this can take a while");
+            let state = WorldState::load_dumps(nations_dump, regions_dump)?;
+            info!("Loaded {} nations and {} regions from the daily dumps", state.nations.len(), state.regions.len());
+            state
+        } else {
+            info!("No world snapshot or dumps found, starting with an empty world state");
+            WorldState::default()
+        };
+
+        *STATE.write().unwrap() = state;
+        ENABLED.store(true, Ordering::Relaxed);
+
+        Ok(Some(Self {
+            snapshot_path,
+            snapshot_interval: Duration::from_secs(world_config.snapshot_interval.unwrap_or(DEFAULT_SNAPSHOT_INTERVAL)),
+            last_snapshot: Instant::now(),
+            infer_origin: world_config.infer_origin.unwrap_or(true),
+        }))
+    }
+
+    // Some happenings lack the region bucket their origin comes from. Called before the event is output,
+    // so the nation is looked up where it was before the event.
+    pub fn infer_origin(&self, event: &mut ParsedEvent) {
+        if !self.infer_origin || event.origin.as_deref() != Some(UNKNOWN_ORIGIN) {
+            return;
+        }
+
+        if let Some(region) = STATE.read().unwrap().nation_region(event) {
+            event.origin = Some(region);
+            event.origin_inferred = true;
+        }
+    }
+
+    pub fn observe(&mut self, event: &ParsedEvent) {
+        STATE.write().unwrap().apply(event);
+    }
+
+    // Snapshots are written on the blocking thread pool, so events keep flowing meanwhile
+    pub fn tick(&mut self) {
+        if self.last_snapshot.elapsed() < self.snapshot_interval {
+            return;
+        }
+
+        self.last_snapshot = Instant::now();
+        let path = self.snapshot_path.clone();
+
+        tokio::task::spawn_blocking(move || {
+            // Skipped if the previous snapshot is still being written
+            if let Ok(_guard) = SNAPSHOT_LOCK.try_lock() {
+                save_snapshot(&path);
+            }
+        });
+    }
+
+    // Called on shutdown
+    pub fn save(&self) {
+        let _guard = SNAPSHOT_LOCK.lock().unwrap();
+        save_snapshot(&self.snapshot_path);
+    }
+}
+
+fn save_snapshot(path: &str) {
+    // Serializing takes much longer than copying, and the output worker can't apply events while the lock is held
+    let state = STATE.read().unwrap().clone();
+
+    let result = serde_json::to_string(&state).map_err(Box::<dyn Error>::from).and_then(|contents| {
+        if let Some(parent) = Path::new(path).parent() && !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Written to a temporary file first, so a crash can't leave a truncated snapshot behind
+        let temporary = format!("{}.tmp", path);
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)?;
+
+        Ok(())
+    });
+
+    if let Err(err) = result {
+        error!("Failed to save world snapshot to '{}': {}", path, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(category: &str, actor: Option<&str>, receptor: Option<&str>, origin: Option<&str>, destination: Option<&str>) -> ParsedEvent {
+        let mut event = ParsedEvent::new(1, 1, category);
+        event.actor = actor.map(String::from);
+        event.receptor = receptor.map(String::from);
+        event.origin = origin.map(String::from);
+        event.destination = destination.map(String::from);
+        event
+    }
+
+    #[test]
+    fn test_apply_events() {
+        let mut state = WorldState::default();
+
+        for event in [
+            event("nfound", Some("a"), None, Some("r"), None),
+            event("nfound", Some("b"), None, Some("r"), None),
+            event("rfound", Some("a"), None, Some("r"), None),
+            event("wadmit", Some("a"), None, Some("r"), None),
+            event("wadmit", Some("b"), None, Some("r"), None),
+            event("wendo", Some("b"), Some("a"), Some("r"), None),
+            event("wendo", Some("a"), Some("b"), Some("r"), None),
+            event("ndel", None, Some("a"), Some("r"), None),
+        ] {
+            state.apply(&event);
+        }
+
+        assert!(state.nations["a"].wa);
+        assert!(state.nations["a"].endorsements.contains("b"));
+        assert_eq!(state.regions["r"].delegate.as_deref(), Some("a"));
+        assert_eq!(state.regions["r"].founder.as_deref(), Some("a"));
+        assert_eq!(state.regions["r"].nations.len(), 2);
+
+        // Moving away drops the delegacy and every endorsement on both sides
+        state.apply(&event("move", Some("a"), None, Some("r"), Some("s")));
+
+        assert_eq!(state.nations["a"].region, "s");
+        assert!(state.nations["a"].endorsements.is_empty());
+        assert!(state.nations["b"].endorsements.is_empty());
+        assert_eq!(state.regions["r"].delegate, None);
+        assert!(state.regions["s"].nations.contains("a"));
+
+        // Happenings without a region bucket, such as an endorsement of a nation unknown to the model
+        assert_eq!(state.nation_region(&event("wendo", Some("x"), Some("a"), Some(UNKNOWN_ORIGIN), None)).as_deref(), Some("s"));
+        state.apply(&event("wadmit", Some("x"), None, Some(UNKNOWN_ORIGIN), None));
+        assert!(!state.nations.contains_key("x"));
+
+        state.apply(&event("ncte", None, Some("b"), Some("r"), None));
+        assert!(!state.nations.contains_key("b"));
+        assert!(state.regions["r"].nations.is_empty());
+    }
+}